@@ -0,0 +1,50 @@
+//! Times the examples of the README, run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use lambda_calculus::interpreter::execute;
+
+const Y: &str = r"(\f.(\x.f (\v.x x v)) (\x.f (\v.x x v)))";
+
+fn bench(name: &str, program: &str, iterations: u32) {
+    let mut total = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let result = execute(program);
+        total += start.elapsed();
+        assert!(result.is_ok(), "{} failed: {:?}", name, result);
+    }
+    println!(
+        "{:<24} {:>12.3?} / iteration ({} iterations)",
+        name,
+        total / iterations,
+        iterations
+    );
+}
+
+fn main() {
+    bench("addition", r"(\x.\y.x+y) 1 2", 10_000);
+    bench("higher order", r"(\f.\x.f x) (\x.x+1) 1", 10_000);
+    bench(
+        "fib 15 (Y-combinator)",
+        &format!(r"{Y} (\f.\x.x<2 ? 1 : (f x-1) + (f x-2)) 15"),
+        20,
+    );
+    bench(
+        "fib 25 (Y-combinator)",
+        &format!(r"{Y} (\f.\x.x<2 ? 1 : (f x-1) + (f x-2)) 25"),
+        3,
+    );
+    bench(
+        "fib 25 (let rec)",
+        r"let rec fib = \x.x<2 ? 1 : (fib x-1) + (fib x-2) in fib 25",
+        3,
+    );
+    bench(
+        "fib 20 (definitions)",
+        &format!(
+            r"let a = 1; let b = 2; let c = 3; let d = 4; let Y = {Y}; let fib = Y (\f.\x.x<2 ? 1 : (f x-1) + (f x-2)); fib 20"
+        ),
+        5,
+    );
+}
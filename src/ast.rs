@@ -1,9 +1,29 @@
-use std::rc::Rc;
+use std::{collections::HashSet, fmt::Display, rc::Rc};
+
+use crate::{printer::Printer, span::Span, types::Type};
+
+/// A node of the syntax tree along with the source code it was parsed from.
+///
+/// Spans are ignored when comparing expressions, so that the same program
+/// written with a different layout gives equal trees.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expr {
+pub enum ExprKind {
+    /// `\var.body`, or `\var: ty.body` when the type of `var` is written.
     Abs {
         var: Rc<str>,
+        ty: Option<Type>,
         body: Rc<Expr>,
     },
     App {
@@ -25,6 +45,18 @@ pub enum Expr {
         true_branch: Rc<Expr>,
         false_branch: Rc<Expr>,
     },
+    /// `lhs && rhs` or `lhs || rhs`, where `rhs` is only evaluated when `lhs`
+    /// doesn't decide the result.
+    Logical {
+        operation: LogicalOp,
+        lhs: Rc<Expr>,
+        rhs: Rc<Expr>,
+    },
+    /// `-operand` or `!operand`.
+    Unary {
+        operation: UnaryOp,
+        operand: Rc<Expr>,
+    },
     Var {
         name: Rc<str>,
     },
@@ -34,6 +66,32 @@ pub enum Expr {
     BooleanLiteral {
         value: bool,
     },
+    /// `let rec name = value in body`, where `name` is bound to `value` in both
+    /// `value` and `body`. `value` is a function. The type of `name` can be
+    /// written as in `let rec name: ty = value in body`.
+    LetRec {
+        name: Rc<str>,
+        ty: Option<Type>,
+        value: Rc<Expr>,
+        body: Rc<Expr>,
+    },
+    /// Placeholder for a part of the program that could not be parsed.
+    Error,
+}
+
+/// A top-level element of a program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// `let name = value`, makes `value` available as `name` in the rest of the program.
+    Definition { name: Rc<str>, value: Expr },
+    /// An expression whose value is shown to the user.
+    Expression(Expr),
+}
+
+/// A program is a sequence of items separated by `;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub items: Vec<Item>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -54,56 +112,291 @@ pub enum ComparisonOp {
     Neq,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOp {
+    /// `-`, the opposite of a number.
+    Neg,
+    /// `!`, the negation of a boolean.
+    Not,
+}
+
+impl Display for ArithmeticOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            ArithmeticOp::Add => "+",
+            ArithmeticOp::Sub => "-",
+            ArithmeticOp::Mul => "*",
+            ArithmeticOp::Div => "/",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for ComparisonOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            ComparisonOp::Gt => ">",
+            ComparisonOp::Gte => ">=",
+            ComparisonOp::Lt => "<",
+            ComparisonOp::Lte => "<=",
+            ComparisonOp::Eq => "=",
+            ComparisonOp::Neq => "!=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for LogicalOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            LogicalOp::And => "&&",
+            LogicalOp::Or => "||",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl ArithmeticOp {
+    /// Priority of the operator among the binary ones, higher binds tighter.
+    ///
+    /// This is the only table of priorities: the parser and the printer both
+    /// use it, so that printed expressions parse back the same way.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            ArithmeticOp::Add | ArithmeticOp::Sub => 5,
+            ArithmeticOp::Mul | ArithmeticOp::Div => 6,
+        }
+    }
+}
+
+impl ComparisonOp {
+    /// Comparisons bind looser than arithmetic, see `ArithmeticOp::precedence`.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            ComparisonOp::Gt | ComparisonOp::Gte | ComparisonOp::Lt | ComparisonOp::Lte => 3,
+            ComparisonOp::Eq | ComparisonOp::Neq => 4,
+        }
+    }
+}
+
+impl LogicalOp {
+    /// `&&` and `||` bind looser than everything else, `&&` tighter than `||`.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            LogicalOp::Or => 1,
+            LogicalOp::And => 2,
+        }
+    }
+}
+
+/// Writes the expression on a single line with as few parentheses as possible,
+/// see `Printer` to break long lines.
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Printer::new().print(self))
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Printer::new().print_item(self))
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Printer::new().print_program(self))
+    }
+}
+
 impl Expr {
+    /// The names used in the expression that are not bound inside of it.
+    pub fn free_vars(&self) -> HashSet<Rc<str>> {
+        self.free_var_uses()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Every use of a name that is not bound inside of the expression, with its
+    /// position, in the order of the source.
+    pub fn free_var_uses(&self) -> Vec<(Rc<str>, Span)> {
+        let mut uses = vec![];
+        self.collect_free_var_uses(&mut vec![], &mut uses);
+        uses
+    }
+
+    fn collect_free_var_uses(&self, bound: &mut Vec<Rc<str>>, uses: &mut Vec<(Rc<str>, Span)>) {
+        match &self.kind {
+            ExprKind::Abs { var, body, .. } => {
+                bound.push(Rc::clone(var));
+                body.collect_free_var_uses(bound, uses);
+                bound.pop();
+            }
+            ExprKind::LetRec {
+                name, value, body, ..
+            } => {
+                bound.push(Rc::clone(name));
+                value.collect_free_var_uses(bound, uses);
+                body.collect_free_var_uses(bound, uses);
+                bound.pop();
+            }
+            ExprKind::Var { name } => {
+                if !bound.contains(name) {
+                    uses.push((Rc::clone(name), self.span));
+                }
+            }
+            ExprKind::App {
+                function: lhs,
+                parameter: rhs,
+            }
+            | ExprKind::Arithmetic { lhs, rhs, .. }
+            | ExprKind::Comparison { lhs, rhs, .. }
+            | ExprKind::Logical { lhs, rhs, .. } => {
+                lhs.collect_free_var_uses(bound, uses);
+                rhs.collect_free_var_uses(bound, uses);
+            }
+            ExprKind::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => {
+                condition.collect_free_var_uses(bound, uses);
+                true_branch.collect_free_var_uses(bound, uses);
+                false_branch.collect_free_var_uses(bound, uses);
+            }
+            ExprKind::Unary { operand, .. } => operand.collect_free_var_uses(bound, uses),
+            ExprKind::NumericLiteral { .. } | ExprKind::BooleanLiteral { .. } | ExprKind::Error => {
+            }
+        }
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind) -> Self {
+        Self {
+            kind,
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Self { span, ..self }
+    }
+
     pub fn abs(var: &str, body: Expr) -> Self {
-        Expr::Abs {
+        Self::new(ExprKind::Abs {
             var: Rc::from(var),
+            ty: None,
             body: Rc::new(body),
-        }
+        })
+    }
+
+    /// `\var: ty.body`
+    pub fn annotated_abs(var: &str, ty: Type, body: Expr) -> Self {
+        Self::new(ExprKind::Abs {
+            var: Rc::from(var),
+            ty: Some(ty),
+            body: Rc::new(body),
+        })
     }
 
     pub fn app(function: Expr, parameter: Expr) -> Self {
-        Expr::App {
+        Self::new(ExprKind::App {
             function: Rc::new(function),
             parameter: Rc::new(parameter),
-        }
+        })
     }
 
     pub fn arithmetic(operation: ArithmeticOp, lhs: Expr, rhs: Expr) -> Self {
-        Expr::Arithmetic {
+        Self::new(ExprKind::Arithmetic {
             operation,
             lhs: Rc::new(lhs),
             rhs: Rc::new(rhs),
-        }
+        })
     }
 
     pub fn comparison(operation: ComparisonOp, lhs: Expr, rhs: Expr) -> Self {
-        Expr::Comparison {
+        Self::new(ExprKind::Comparison {
             operation,
             lhs: Rc::new(lhs),
             rhs: Rc::new(rhs),
-        }
+        })
     }
 
     pub fn conditional(condition: Expr, true_branch: Expr, false_branch: Expr) -> Self {
-        Expr::Conditional {
+        Self::new(ExprKind::Conditional {
             condition: Rc::new(condition),
             true_branch: Rc::new(true_branch),
             false_branch: Rc::new(false_branch),
-        }
+        })
+    }
+
+    pub fn logical(operation: LogicalOp, lhs: Expr, rhs: Expr) -> Self {
+        Self::new(ExprKind::Logical {
+            operation,
+            lhs: Rc::new(lhs),
+            rhs: Rc::new(rhs),
+        })
+    }
+
+    pub fn unary(operation: UnaryOp, operand: Expr) -> Self {
+        Self::new(ExprKind::Unary {
+            operation,
+            operand: Rc::new(operand),
+        })
     }
 
     pub fn var(name: &str) -> Self {
-        Expr::Var {
+        Self::new(ExprKind::Var {
             name: Rc::from(name),
-        }
+        })
     }
 
     pub fn numeric_literal(value: f64) -> Self {
-        Expr::NumericLiteral { value }
+        Self::new(ExprKind::NumericLiteral { value })
     }
 
     pub fn boolean_literal(value: bool) -> Self {
-        Expr::BooleanLiteral { value }
+        Self::new(ExprKind::BooleanLiteral { value })
+    }
+
+    pub fn let_rec(name: &str, value: Expr, body: Expr) -> Self {
+        Self::new(ExprKind::LetRec {
+            name: Rc::from(name),
+            ty: None,
+            value: Rc::new(value),
+            body: Rc::new(body),
+        })
+    }
+
+    /// `let rec name: ty = value in body`
+    pub fn annotated_let_rec(name: &str, ty: Type, value: Expr, body: Expr) -> Self {
+        Self::new(ExprKind::LetRec {
+            name: Rc::from(name),
+            ty: Some(ty),
+            value: Rc::new(value),
+            body: Rc::new(body),
+        })
+    }
+
+    pub fn error() -> Self {
+        Self::new(ExprKind::Error)
     }
 }
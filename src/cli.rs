@@ -0,0 +1,265 @@
+use std::{error::Error, fmt::Display, time::Duration};
+
+use lambda_calculus::{
+    interpreter::{limits::Limits, reduce::Order, ClosureStyle, Strategy},
+    printer::Symbols,
+    types::TypeSystem,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct CliError(String);
+
+impl Error for CliError {}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Error: {}", self.0)
+    }
+}
+
+/// What the command line asks for.
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    /// The program to run, the interactive mode is used if there is none.
+    pub file: Option<String>,
+    pub strategy: Strategy,
+    /// Programs are reduced to their normal form in this order instead of being evaluated.
+    pub reduce: Option<Order>,
+    /// Programs are reduced and each reduction step is printed.
+    pub trace: bool,
+    /// Programs are only checked for unbound names, without being evaluated.
+    pub check: bool,
+    /// The types of programs are found with this system, and the programs
+    /// that can't be typed are rejected before evaluation.
+    pub typecheck: Option<TypeSystem>,
+    pub limits: Limits,
+    /// How functions are shown in results.
+    pub closures: ClosureStyle,
+    /// The symbols used to write expressions and types in results.
+    pub symbols: Symbols,
+}
+
+/// Reads the options from the arguments, without the name of the executable.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, CliError> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strategy" => {
+                options.strategy = match value(&arg, args.next())?.as_str() {
+                    "value" => Strategy::CallByValue,
+                    "name" => Strategy::CallByName,
+                    "need" => Strategy::CallByNeed,
+                    other => {
+                        return Err(CliError(format!(
+                            "unknown strategy `{}`, expected `value`, `name` or `need`",
+                            other
+                        )))
+                    }
+                };
+            }
+            "--reduce" => {
+                options.reduce = match value(&arg, args.next())?.as_str() {
+                    "normal" => Some(Order::Normal),
+                    "applicative" => Some(Order::Applicative),
+                    other => {
+                        return Err(CliError(format!(
+                            "unknown order `{}`, expected `normal` or `applicative`",
+                            other
+                        )))
+                    }
+                };
+            }
+            "--trace" => options.trace = true,
+            "--check" => options.check = true,
+            "--typecheck" => options.typecheck = Some(TypeSystem::Inferred),
+            "--simply-typed" => options.typecheck = Some(TypeSystem::Simple),
+            "--closures" => {
+                options.closures = match value(&arg, args.next())?.as_str() {
+                    "substitute" => ClosureStyle::Substituted,
+                    "environment" => ClosureStyle::Environment,
+                    other => {
+                        return Err(CliError(format!(
+                            "unknown closure style `{}`, expected `substitute` or `environment`",
+                            other
+                        )))
+                    }
+                };
+            }
+            "--symbols" => {
+                options.symbols = match value(&arg, args.next())?.as_str() {
+                    "ascii" => Symbols::Ascii,
+                    "unicode" => Symbols::Unicode,
+                    other => {
+                        return Err(CliError(format!(
+                            "unknown symbols `{}`, expected `ascii` or `unicode`",
+                            other
+                        )))
+                    }
+                };
+            }
+            "--max-steps" => {
+                let steps = value(&arg, args.next())?;
+                let steps = steps
+                    .parse()
+                    .map_err(|_| CliError(format!("invalid number of steps `{}`", steps)))?;
+                options.limits.max_steps = Some(steps);
+            }
+            "--timeout" => {
+                let seconds = value(&arg, args.next())?;
+                let timeout = seconds
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .ok_or_else(|| CliError(format!("invalid timeout `{}`", seconds)))?;
+                options.limits.timeout = Some(timeout);
+            }
+            _ if arg.starts_with("--") => {
+                return Err(CliError(format!("unknown option `{}`", arg)));
+            }
+            _ if options.file.is_some() => {
+                return Err(CliError("too many parameters".to_string()));
+            }
+            _ => options.file = Some(arg),
+        }
+    }
+    // Reductions allow free variables and are not checked
+    if options.reduce.is_some() || options.trace {
+        if options.check {
+            return Err(CliError(
+                "`--check` can't be used with `--reduce` or `--trace`".to_owned(),
+            ));
+        }
+        if options.typecheck.is_some() {
+            return Err(CliError(
+                "`--typecheck` and `--simply-typed` can't be used with `--reduce` or `--trace`"
+                    .to_owned(),
+            ));
+        }
+    }
+    Ok(options)
+}
+
+/// The rest of the interactive `line` if it starts with the command `name`, followed by
+/// whitespace or nothing.
+pub fn command<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(name)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim_start())
+    } else {
+        None
+    }
+}
+
+fn value(option: &str, value: Option<String>) -> Result<String, CliError> {
+    value.ok_or_else(|| CliError(format!("missing value for `{}`", option)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::cli::*;
+
+    fn args(args: &str) -> Result<Options, CliError> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_limits() {
+        let options = args("--max-steps 1000 prog.lc --timeout 2.5").unwrap();
+        assert_eq!(options.file.as_deref(), Some("prog.lc"));
+        assert_eq!(options.limits.max_steps, Some(1000));
+        assert_eq!(options.limits.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(args("").unwrap(), Options::default());
+    }
+
+    #[test]
+    fn test_strategy() {
+        assert_eq!(
+            args("--strategy need").unwrap().strategy,
+            Strategy::CallByNeed
+        );
+        assert_eq!(
+            args("--strategy name").unwrap().strategy,
+            Strategy::CallByName
+        );
+        assert_eq!(args("").unwrap().strategy, Strategy::CallByValue);
+    }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(args("--reduce normal").unwrap().reduce, Some(Order::Normal));
+        assert_eq!(
+            args("--reduce applicative").unwrap().reduce,
+            Some(Order::Applicative)
+        );
+        assert_eq!(args("").unwrap().reduce, None);
+        assert!(args("--reduce lazy").is_err());
+        assert!(args("--trace prog.lc").unwrap().trace);
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(args("prog.lc --check").unwrap().check);
+        assert!(!args("prog.lc").unwrap().check);
+        let options = args("--check --typecheck").unwrap();
+        assert!(options.check);
+        assert_eq!(options.typecheck, Some(TypeSystem::Inferred));
+        assert_eq!(
+            args("--simply-typed").unwrap().typecheck,
+            Some(TypeSystem::Simple)
+        );
+        assert_eq!(args("").unwrap().typecheck, None);
+        for combination in [
+            "--reduce normal --check",
+            "--trace --typecheck",
+            "--simply-typed --reduce applicative",
+        ] {
+            assert!(args(combination).is_err(), "{}", combination);
+        }
+    }
+
+    #[test]
+    fn test_closures() {
+        assert_eq!(
+            args("--closures environment").unwrap().closures,
+            ClosureStyle::Environment
+        );
+        assert_eq!(
+            args("--closures substitute").unwrap().closures,
+            ClosureStyle::Substituted
+        );
+        assert_eq!(args("").unwrap().closures, ClosureStyle::Substituted);
+        assert!(args("--closures source").is_err());
+    }
+
+    #[test]
+    fn test_symbols() {
+        assert_eq!(args("--symbols unicode").unwrap().symbols, Symbols::Unicode);
+        assert_eq!(args("--symbols ascii").unwrap().symbols, Symbols::Ascii);
+        assert_eq!(args("").unwrap().symbols, Symbols::Ascii);
+        assert!(args("--symbols latex").is_err());
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(command(":trace (\\x.x) 1", ":trace"), Some(r"(\x.x) 1"));
+        assert_eq!(command(":type   x", ":type"), Some("x"));
+        assert_eq!(command(":type", ":type"), Some(""));
+        assert_eq!(command(":tracex", ":trace"), None);
+        assert_eq!(command(":typefoo", ":type"), None);
+        assert_eq!(command("x", ":type"), None);
+    }
+
+    #[test]
+    fn test_invalid_args() {
+        assert!(args("--max-steps").is_err());
+        assert!(args("--max-steps -1").is_err());
+        assert!(args("--timeout never").is_err());
+        assert!(args("--timeout -1").is_err());
+        assert!(args("--steps 10").is_err());
+        assert!(args("--strategy lazy").is_err());
+        assert!(args("a.lc b.lc").is_err());
+    }
+}
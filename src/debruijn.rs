@@ -0,0 +1,404 @@
+//! Nameless representation of expressions, where bound variables are replaced
+//! by de Bruijn indices : the number of binders between a variable and the one
+//! that binds it. Expressions that only differ by the names of their bound
+//! variables have the same representation.
+
+use std::{collections::HashSet, rc::Rc};
+
+use crate::{
+    ast::{ArithmeticOp, ComparisonOp, Expr, ExprKind, LogicalOp, UnaryOp},
+    types::Type,
+};
+
+/// An expression without names for bound variables. Type annotations are kept.
+///
+/// `\x.\y.x y` is `Abs(Abs(App(Bound(1), Bound(0))))`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Abs {
+        ty: Option<Type>,
+        body: Rc<Term>,
+    },
+    App {
+        function: Rc<Term>,
+        parameter: Rc<Term>,
+    },
+    Arithmetic {
+        operation: ArithmeticOp,
+        lhs: Rc<Term>,
+        rhs: Rc<Term>,
+    },
+    Comparison {
+        operation: ComparisonOp,
+        lhs: Rc<Term>,
+        rhs: Rc<Term>,
+    },
+    Conditional {
+        condition: Rc<Term>,
+        true_branch: Rc<Term>,
+        false_branch: Rc<Term>,
+    },
+    Logical {
+        operation: LogicalOp,
+        lhs: Rc<Term>,
+        rhs: Rc<Term>,
+    },
+    Unary {
+        operation: UnaryOp,
+        operand: Rc<Term>,
+    },
+    /// A variable bound by the `index`-th enclosing binder, starting from 0.
+    Bound {
+        index: usize,
+    },
+    /// A variable that is not bound in the expression, which keeps its name.
+    Free {
+        name: Rc<str>,
+    },
+    NumericLiteral {
+        value: f64,
+    },
+    BooleanLiteral {
+        value: bool,
+    },
+    /// `let rec` binds a single variable in both `value` and `body`.
+    LetRec {
+        ty: Option<Type>,
+        value: Rc<Term>,
+        body: Rc<Term>,
+    },
+    Error,
+}
+
+impl From<&Expr> for Term {
+    fn from(expr: &Expr) -> Self {
+        Term::from_expr(expr, &mut vec![])
+    }
+}
+
+impl Term {
+    /// `bound` holds the names of the enclosing binders, the innermost last.
+    fn from_expr(expr: &Expr, bound: &mut Vec<Rc<str>>) -> Self {
+        let mut convert = |expr: &Expr| Rc::new(Term::from_expr(expr, bound));
+        match &expr.kind {
+            ExprKind::Abs { var, ty, body } => {
+                bound.push(Rc::clone(var));
+                let body = Rc::new(Term::from_expr(body, bound));
+                bound.pop();
+                Term::Abs {
+                    ty: ty.clone(),
+                    body,
+                }
+            }
+            ExprKind::LetRec {
+                name,
+                ty,
+                value,
+                body,
+            } => {
+                bound.push(Rc::clone(name));
+                let value = Rc::new(Term::from_expr(value, bound));
+                let body = Rc::new(Term::from_expr(body, bound));
+                bound.pop();
+                Term::LetRec {
+                    ty: ty.clone(),
+                    value,
+                    body,
+                }
+            }
+            ExprKind::Var { name } => match bound.iter().rev().position(|var| var == name) {
+                Some(index) => Term::Bound { index },
+                None => Term::Free {
+                    name: Rc::clone(name),
+                },
+            },
+            ExprKind::App {
+                function,
+                parameter,
+            } => Term::App {
+                function: convert(function),
+                parameter: convert(parameter),
+            },
+            ExprKind::Arithmetic {
+                operation,
+                lhs,
+                rhs,
+            } => Term::Arithmetic {
+                operation: operation.clone(),
+                lhs: convert(lhs),
+                rhs: convert(rhs),
+            },
+            ExprKind::Comparison {
+                operation,
+                lhs,
+                rhs,
+            } => Term::Comparison {
+                operation: operation.clone(),
+                lhs: convert(lhs),
+                rhs: convert(rhs),
+            },
+            ExprKind::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => Term::Conditional {
+                condition: convert(condition),
+                true_branch: convert(true_branch),
+                false_branch: convert(false_branch),
+            },
+            ExprKind::Logical {
+                operation,
+                lhs,
+                rhs,
+            } => Term::Logical {
+                operation: operation.clone(),
+                lhs: convert(lhs),
+                rhs: convert(rhs),
+            },
+            ExprKind::Unary { operation, operand } => Term::Unary {
+                operation: operation.clone(),
+                operand: convert(operand),
+            },
+            ExprKind::NumericLiteral { value } => Term::NumericLiteral { value: *value },
+            ExprKind::BooleanLiteral { value } => Term::BooleanLiteral { value: *value },
+            ExprKind::Error => Term::Error,
+        }
+    }
+
+    /// Gives names back to the bound variables. The variable bound at depth `n`
+    /// is named `vn`, with more `v`s in front if a free variable already has
+    /// that name.
+    pub fn to_expr(&self) -> Expr {
+        self.named(&self.free_vars(), &mut vec![])
+    }
+
+    /// The names of the free variables of the term.
+    pub fn free_vars(&self) -> HashSet<Rc<str>> {
+        let mut free = HashSet::new();
+        self.collect_free_vars(&mut free);
+        free
+    }
+
+    fn collect_free_vars(&self, free: &mut HashSet<Rc<str>>) {
+        match self {
+            Term::Free { name } => {
+                free.insert(Rc::clone(name));
+            }
+            Term::Abs { body, .. } | Term::Unary { operand: body, .. } => {
+                body.collect_free_vars(free)
+            }
+            Term::App {
+                function: lhs,
+                parameter: rhs,
+            }
+            | Term::Arithmetic { lhs, rhs, .. }
+            | Term::Comparison { lhs, rhs, .. }
+            | Term::Logical { lhs, rhs, .. }
+            | Term::LetRec {
+                value: lhs,
+                body: rhs,
+                ..
+            } => {
+                lhs.collect_free_vars(free);
+                rhs.collect_free_vars(free);
+            }
+            Term::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => {
+                condition.collect_free_vars(free);
+                true_branch.collect_free_vars(free);
+                false_branch.collect_free_vars(free);
+            }
+            Term::Bound { .. }
+            | Term::NumericLiteral { .. }
+            | Term::BooleanLiteral { .. }
+            | Term::Error => {}
+        }
+    }
+
+    /// `bound` holds the names given to the enclosing binders, the innermost last.
+    fn named(&self, free: &HashSet<Rc<str>>, bound: &mut Vec<Rc<str>>) -> Expr {
+        let mut convert = |term: &Term| term.named(free, bound);
+        match self {
+            Term::Abs { ty, body } => {
+                let var = fresh_name(bound.len(), free);
+                bound.push(Rc::clone(&var));
+                let body = body.named(free, bound);
+                bound.pop();
+                Expr::new(ExprKind::Abs {
+                    var,
+                    ty: ty.clone(),
+                    body: Rc::new(body),
+                })
+            }
+            Term::LetRec { ty, value, body } => {
+                let name = fresh_name(bound.len(), free);
+                bound.push(Rc::clone(&name));
+                let value = value.named(free, bound);
+                let body = body.named(free, bound);
+                bound.pop();
+                Expr::new(ExprKind::LetRec {
+                    name,
+                    ty: ty.clone(),
+                    value: Rc::new(value),
+                    body: Rc::new(body),
+                })
+            }
+            Term::Bound { index } => match bound.iter().rev().nth(*index) {
+                Some(name) => Expr::var(name),
+                // A dangling index, which conversions never create.
+                None => Expr::error(),
+            },
+            Term::Free { name } => Expr::var(name),
+            Term::App {
+                function,
+                parameter,
+            } => Expr::app(convert(function), convert(parameter)),
+            Term::Arithmetic {
+                operation,
+                lhs,
+                rhs,
+            } => Expr::arithmetic(operation.clone(), convert(lhs), convert(rhs)),
+            Term::Comparison {
+                operation,
+                lhs,
+                rhs,
+            } => Expr::comparison(operation.clone(), convert(lhs), convert(rhs)),
+            Term::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => Expr::conditional(
+                convert(condition),
+                convert(true_branch),
+                convert(false_branch),
+            ),
+            Term::Logical {
+                operation,
+                lhs,
+                rhs,
+            } => Expr::logical(operation.clone(), convert(lhs), convert(rhs)),
+            Term::Unary { operation, operand } => Expr::unary(operation.clone(), convert(operand)),
+            Term::NumericLiteral { value } => Expr::numeric_literal(*value),
+            Term::BooleanLiteral { value } => Expr::boolean_literal(*value),
+            Term::Error => Expr::error(),
+        }
+    }
+}
+
+/// The name of the variable bound at `depth`. Names at different depths end with
+/// different numbers, so they never shadow each other.
+fn fresh_name(depth: usize, free: &HashSet<Rc<str>>) -> Rc<str> {
+    let mut name = format!("v{}", depth);
+    while free.contains(name.as_str()) {
+        name.insert(0, 'v');
+    }
+    Rc::from(name)
+}
+
+impl Expr {
+    /// Whether the expressions are equal up to the names of their bound variables,
+    /// like `\x.x` and `\y.y`. Spans are ignored.
+    pub fn alpha_eq(&self, other: &Expr) -> bool {
+        Term::from(self) == Term::from(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{debruijn::*, lexer::lexer, parser::parse};
+
+    fn term(program: &str) -> Term {
+        Term::from(&expr(program))
+    }
+
+    fn expr(program: &str) -> Expr {
+        parse(&lexer(program).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_indices() {
+        let bound = |index| Rc::new(Term::Bound { index });
+        assert_eq!(
+            term(r"\x.\y.x y"),
+            Term::Abs {
+                ty: None,
+                body: Rc::new(Term::Abs {
+                    ty: None,
+                    body: Rc::new(Term::App {
+                        function: bound(1),
+                        parameter: bound(0),
+                    })
+                })
+            }
+        );
+        assert_eq!(
+            term(r"\x.y"),
+            Term::Abs {
+                ty: None,
+                body: Rc::new(Term::Free {
+                    name: Rc::from("y")
+                })
+            }
+        );
+        assert_eq!(
+            term(r"let rec f = \x.f x in f"),
+            Term::LetRec {
+                ty: None,
+                value: Rc::new(Term::Abs {
+                    ty: None,
+                    body: Rc::new(Term::App {
+                        function: bound(1),
+                        parameter: bound(0),
+                    })
+                }),
+                body: bound(0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_alpha_eq() {
+        for (a, b) in [
+            (r"\x.x", r"\y.y"),
+            (r"\x.\y.x y", r"\a.\b.a b"),
+            (r"\x.\x.x", r"\y.\z.z"),
+            (r"\x.x + z", r"\y.y + z"),
+            (r"let rec f = \x.f x in f 1", r"let rec g = \y.g y in g 1"),
+            (r"(\x.x) (1 + 2)", r"(\y.y) (1+2)"),
+        ] {
+            assert!(expr(a).alpha_eq(&expr(b)), "{} and {}", a, b);
+        }
+        for (a, b) in [
+            (r"\x.\y.x", r"\x.\y.y"),
+            (r"\x.y", r"\x.z"),
+            (r"\x.y", r"\y.y"),
+            (r"1 + 2", r"1 - 2"),
+        ] {
+            assert!(!expr(a).alpha_eq(&expr(b)), "{} and {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_to_expr() {
+        assert_eq!(term(r"\x.\y.x y").to_expr().to_string(), r"\v0.\v1.v0 v1");
+        assert_eq!(term(r"\x.v0 x").to_expr().to_string(), r"\vv0.v0 vv0");
+        assert_eq!(
+            term(r"let rec f = \x.f x in f").to_expr().to_string(),
+            r"let rec v0 = \v1.v0 v1 in v0"
+        );
+        for program in [
+            r"\x.\x.x",
+            r"\x.\y.y x ? x + 1 : y * 2",
+            r"(\f.\x.f (f x)) (\y.y) z",
+            r"\v0.\vv0.v0 vv0 v1",
+        ] {
+            let expr = expr(program);
+            let named = Term::from(&expr).to_expr();
+            assert!(expr.alpha_eq(&named), "{} and {}", expr, named);
+            assert_eq!(Term::from(&named), Term::from(&expr));
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt::Write;
+
+use crate::span::Span;
+
+/// A part of the source code pointed at by a diagnostic, with a short explanation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// An error report that can be rendered as an annotated snippet of the source code,
+/// in the style of rustc:
+///
+/// ```text
+/// error: unknown name `y`
+///  --> example.lc:1:7
+///   |
+/// 1 | (\x.x+y) 1
+///   |       ^ this name is not bound to any value
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub label: Option<Label>,
+    pub help: Option<String>,
+}
+
+/// Errors that can be reported to the user as a [`Diagnostic`].
+pub trait ToDiagnostic {
+    fn to_diagnostic(&self) -> Diagnostic;
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            label: None,
+            help: None,
+        }
+    }
+
+    pub fn with_label(self, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            label: Some(Label {
+                span,
+                message: message.into(),
+            }),
+            ..self
+        }
+    }
+
+    pub fn with_help(self, help: impl Into<String>) -> Self {
+        Self {
+            help: Some(help.into()),
+            ..self
+        }
+    }
+
+    /// Renders the diagnostic, `source` must be the program the spans refer to.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let mut out = format!("error: {}\n", self.message);
+        let Some(label) = &self.label else {
+            if let Some(help) = &self.help {
+                let _ = writeln!(out, "  = help: {}", help);
+            }
+            return out;
+        };
+
+        let span = label.span;
+        let lines: Vec<&str> = source.split('\n').collect();
+        let first = span.start.line;
+        let mut last = span.end.line.max(first);
+        // A span ending right after a line break doesn't cover the next line
+        if last > first && span.end.column == 1 {
+            last -= 1;
+        }
+        let gutter = " ".repeat(last.to_string().len());
+
+        let _ = writeln!(
+            out,
+            "{}--> {}:{}:{}",
+            gutter, file_name, span.start.line, span.start.column
+        );
+        let _ = writeln!(out, "{} |", gutter);
+        for line_number in first..=last {
+            let line = lines
+                .get(line_number - 1)
+                .copied()
+                .unwrap_or("")
+                .trim_end_matches('\r');
+            let _ = writeln!(out, "{:>2$} | {}", line_number, line, gutter.len());
+
+            let from = if line_number == first {
+                span.start.column
+            } else {
+                1
+            };
+            let to = if line_number == span.end.line {
+                span.end.column
+            } else {
+                line.chars().count() + 1
+            };
+            // Tabs are kept so that the carets line up with the source line
+            let padding: String = line
+                .chars()
+                .take(from - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = "^".repeat(to.saturating_sub(from).max(1));
+            let _ = write!(out, "{} | {}{}", gutter, padding, carets);
+            if line_number == last {
+                let _ = write!(out, " {}", label.message);
+            }
+            out.push('\n');
+        }
+        if let Some(help) = &self.help {
+            let _ = writeln!(out, "{} |", gutter);
+            let _ = writeln!(out, "{} = help: {}", gutter, help);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{diagnostic::*, span::Position};
+
+    fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Span {
+        Span::new(
+            Position {
+                offset: start.0,
+                line: start.1,
+                column: start.2,
+            },
+            Position {
+                offset: end.0,
+                line: end.1,
+                column: end.2,
+            },
+        )
+    }
+
+    #[test]
+    fn test_single_line() {
+        let diagnostic = Diagnostic::new("unknown name `y`")
+            .with_label(span((6, 1, 7), (7, 1, 8)), "not bound")
+            .with_help("bind it");
+        assert_eq!(
+            diagnostic.render("test.lc", "(\\x.x+y) 1"),
+            "error: unknown name `y`
+ --> test.lc:1:7
+  |
+1 | (\\x.x+y) 1
+  |       ^ not bound
+  |
+  = help: bind it
+"
+        );
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let diagnostic =
+            Diagnostic::new("type error").with_label(span((2, 1, 3), (9, 2, 4)), "here");
+        assert_eq!(
+            diagnostic.render("test.lc", "a bcde\nfgh ij"),
+            "error: type error
+ --> test.lc:1:3
+  |
+1 | a bcde
+  |   ^^^^
+2 | fgh ij
+  | ^^^ here
+"
+        );
+    }
+
+    #[test]
+    fn test_without_label() {
+        let diagnostic = Diagnostic::new("the provided program is empty");
+        assert_eq!(
+            diagnostic.render("test.lc", ""),
+            "error: the provided program is empty\n"
+        );
+    }
+}
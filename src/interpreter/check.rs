@@ -0,0 +1,116 @@
+//! Static checks made on programs before they are evaluated.
+
+use std::{collections::HashSet, rc::Rc};
+
+use crate::{
+    ast::{Item, Program},
+    interpreter::error::InterpreterError,
+};
+
+/// A `NameError` for every use of a name that is bound neither in `program`
+/// nor by `is_defined`, in the order of the source.
+///
+/// Unlike evaluation, this also finds names in branches that are never taken
+/// and in functions that are never called.
+pub fn unbound_names(
+    program: &Program,
+    is_defined: impl Fn(&str) -> bool,
+) -> Vec<InterpreterError> {
+    let mut defined: Vec<&Rc<str>> = vec![];
+    let mut errors = vec![];
+    for item in &program.items {
+        let expr = match item {
+            Item::Definition { value, .. } => value,
+            Item::Expression(expr) => expr,
+        };
+        for (name, span) in expr.free_var_uses() {
+            if !defined.contains(&&name) && !is_defined(&name) {
+                errors.push(InterpreterError::new_name_error(&name, span));
+            }
+        }
+        if let Item::Definition { name, .. } = item {
+            defined.push(name);
+        }
+    }
+    errors
+}
+
+/// Checks programs one after the other, knowing the definitions made by the
+/// previous ones without evaluating them.
+#[derive(Debug, Clone, Default)]
+pub struct Checker {
+    defined: HashSet<Rc<str>>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `name` known to the following programs.
+    pub fn define(&mut self, name: Rc<str>) {
+        self.defined.insert(name);
+    }
+
+    /// Reports every unbound name of `program`. Its definitions are only kept if
+    /// there is none.
+    pub fn check(&mut self, program: &Program) -> Result<(), Vec<InterpreterError>> {
+        let errors = unbound_names(program, |name| self.defined.contains(name));
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        for item in &program.items {
+            if let Item::Definition { name, .. } = item {
+                self.define(Rc::clone(name));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interpreter::check::*, lexer::lexer, parser::parse_program};
+
+    /// The unbound names with the offsets where they are used.
+    fn check(checker: &mut Checker, program: &str) -> Result<(), Vec<(String, usize, usize)>> {
+        let program = parse_program(&lexer(program).unwrap()).unwrap();
+        checker.check(&program).map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| match error {
+                    InterpreterError::NameError { name, span } => {
+                        (name, span.start.offset, span.end.offset)
+                    }
+                    other => panic!("unexpected error {}", other),
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_unbound_names() {
+        let mut checker = Checker::new();
+        assert_eq!(check(&mut checker, r"(\x.x) 1"), Ok(()));
+        assert_eq!(
+            check(&mut checker, r"true ? 1 : fo + \x.x y"),
+            Err(vec![("fo".to_string(), 11, 13), ("y".to_string(), 21, 22)])
+        );
+        assert_eq!(
+            check(&mut checker, r"let rec f = \x.f x in f; f"),
+            Err(vec![("f".to_string(), 25, 26)])
+        );
+    }
+
+    #[test]
+    fn test_definitions() {
+        let mut checker = Checker::new();
+        assert_eq!(check(&mut checker, r"let id = \x.x; id 1"), Ok(()));
+        assert_eq!(check(&mut checker, r"id 2"), Ok(()));
+        assert_eq!(
+            check(&mut checker, r"let f = g; let g = 1"),
+            Err(vec![("g".to_string(), 8, 9)])
+        );
+        assert!(check(&mut checker, r"g").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{ast::Expr, interpreter::EvalResult};
+
+/// What a name is bound to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    Value(EvalResult),
+    /// An argument that is evaluated when the name is used, see `Strategy`.
+    Thunk(Rc<Thunk>),
+}
+
+impl From<EvalResult> for Binding {
+    fn from(value: EvalResult) -> Self {
+        Self::Value(value)
+    }
+}
+
+/// An expression whose evaluation is delayed until its value is needed.
+#[derive(Debug, PartialEq)]
+pub struct Thunk {
+    pub expr: Rc<Expr>,
+    pub env: Env,
+    /// Whether the value is kept after the first evaluation, otherwise the
+    /// expression is evaluated each time the value is needed.
+    memoize: bool,
+    value: RefCell<Option<EvalResult>>,
+}
+
+impl Thunk {
+    pub fn new(expr: Rc<Expr>, env: Env, memoize: bool) -> Rc<Self> {
+        Rc::new(Self {
+            expr,
+            env,
+            memoize,
+            value: RefCell::new(None),
+        })
+    }
+
+    /// The value of the expression, if it has already been evaluated and memoized.
+    pub fn value(&self) -> Option<EvalResult> {
+        self.value.borrow().clone()
+    }
+
+    pub fn memoize(&self) -> bool {
+        self.memoize
+    }
+
+    /// Remembers `value` as the value of the expression, if the thunk memoizes.
+    pub fn update(&self, value: &EvalResult) {
+        if self.memoize {
+            *self.value.borrow_mut() = Some(value.clone());
+        }
+    }
+}
+
+/// Bindings from names to values, shared between closures.
+///
+/// The environment is a linked list of frames: binding a name creates a new
+/// frame pointing to the existing ones, so capturing or extending an
+/// environment never copies it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Env {
+    head: Option<Rc<Frame>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Frame {
+    name: Rc<str>,
+    value: Binding,
+    parent: Env,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a new environment where `name` is bound to `value`, shadowing any
+    /// previous binding of `name`. `self` is left untouched.
+    pub fn bind(&self, name: Rc<str>, value: impl Into<Binding>) -> Self {
+        Self {
+            head: Some(Rc::new(Frame {
+                name,
+                value: value.into(),
+                parent: self.clone(),
+            })),
+        }
+    }
+
+    /// The most recent binding of `name`.
+    pub fn get(&self, name: &str) -> Option<&Binding> {
+        let mut current = self.head.as_ref();
+        while let Some(frame) = current {
+            if *frame.name == *name {
+                return Some(&frame.value);
+            }
+            current = frame.parent.head.as_ref();
+        }
+        None
+    }
+}
+
+impl Drop for Env {
+    /// Frames are freed in a loop instead of recursively, so that dropping
+    /// a long environment cannot overflow the stack. The environments held
+    /// only by the values of the frames are freed in the same loop, as each
+    /// delayed argument holds the environment of the call that made it.
+    fn drop(&mut self) {
+        let mut pending = vec![self.head.take()];
+        while let Some(mut head) = pending.pop() {
+            while let Some(frame) = head {
+                match Rc::try_unwrap(frame) {
+                    Ok(mut frame) => {
+                        frame.value.release(&mut pending);
+                        head = frame.parent.head.take();
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+}
+
+impl Binding {
+    /// Moves to `pending` the environments that are only held by this binding.
+    fn release(&mut self, pending: &mut Vec<Option<Rc<Frame>>>) {
+        let value = match self {
+            Binding::Value(value) => value,
+            Binding::Thunk(thunk) => {
+                let Some(thunk) = Rc::get_mut(thunk) else {
+                    return;
+                };
+                pending.push(thunk.env.head.take());
+                match thunk.value.get_mut() {
+                    Some(value) => value,
+                    None => return,
+                }
+            }
+        };
+        if let EvalResult::Closure { context, .. } = value {
+            pending.push(context.head.take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, thread};
+
+    use crate::{
+        ast::Expr,
+        interpreter::{env::*, limits::Limits, EvalResult, Strategy},
+        lexer::lexer,
+        parser::parse,
+    };
+
+    #[test]
+    fn test_shadowing() {
+        let env = Env::new().bind("x".into(), EvalResult::Value(1.));
+        let shadowed = env.bind("x".into(), EvalResult::Value(2.));
+        assert_eq!(
+            shadowed.get("x"),
+            Some(&Binding::Value(EvalResult::Value(2.)))
+        );
+        assert_eq!(env.get("x"), Some(&Binding::Value(EvalResult::Value(1.))));
+        assert_eq!(env.get("y"), None);
+    }
+
+    #[test]
+    fn test_thunk_update() {
+        let expr = Rc::new(Expr::numeric_literal(1.));
+        let by_need = Thunk::new(Rc::clone(&expr), Env::new(), true);
+        let by_name = Thunk::new(expr, Env::new(), false);
+        for thunk in [&by_need, &by_name] {
+            assert_eq!(thunk.value(), None);
+            thunk.update(&EvalResult::Value(1.));
+        }
+        assert_eq!(by_need.value(), Some(EvalResult::Value(1.)));
+        assert_eq!(by_name.value(), None);
+    }
+
+    /// Each argument holds the environment of the call that made it, and so
+    /// the argument of the previous call.
+    #[test]
+    fn test_long_chain_of_thunks() {
+        // Arguments are evaluated as many times as they are used by name
+        for (strategy, n) in [(Strategy::CallByNeed, 100_000), (Strategy::CallByName, 500)] {
+            let program = format!(r"let rec f = \n.n=0 ? 0 : f (n-1) in f {}", n);
+            // A stack that freeing the arguments recursively would overflow
+            let evaluation = thread::Builder::new().stack_size(64 * 1024).spawn(move || {
+                let expr = parse(&lexer(&program).unwrap()).unwrap();
+                let result = expr.eval_machine_with(strategy, Limits::default());
+                assert_eq!(result, Ok(EvalResult::Value(0.)), "{:?}", strategy);
+            });
+            evaluation.unwrap().join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_long_environment() {
+        let mut env = Env::new();
+        for i in 0..100_000 {
+            env = env.bind("x".into(), EvalResult::Value(i as f64));
+        }
+        assert_eq!(
+            env.get("x"),
+            Some(&Binding::Value(EvalResult::Value(99_999.)))
+        );
+    }
+}
@@ -1,26 +1,49 @@
 use std::{error::Error, fmt::Display};
 
+use crate::{
+    diagnostic::{Diagnostic, ToDiagnostic},
+    interpreter::limits::Limit,
+    lexer::error::LexerError,
+    parser::error::SyntaxError,
+    span::Span,
+    types::error::TypeError,
+};
+
 #[derive(Debug, PartialEq)]
 pub enum InterpreterError {
     TypeError {
         wanted_type: String,
         given_type: String,
+        span: Span,
     },
     NameError {
         name: String,
+        span: Span,
+    },
+    /// The expression contains a part that could not be parsed.
+    InvalidExpression {
+        span: Span,
+    },
+    /// The evaluation was stopped because it went over `limit` while evaluating
+    /// the expression at `span`.
+    ResourceExhausted {
+        limit: Limit,
+        span: Span,
     },
 }
 
 impl InterpreterError {
-    pub fn new_type_error(wanted_type: &str, given_type: &str) -> Self {
+    pub fn new_type_error(wanted_type: &str, given_type: &str, span: Span) -> Self {
         Self::TypeError {
             wanted_type: wanted_type.to_owned(),
             given_type: given_type.to_owned(),
+            span,
         }
     }
-    pub fn new_name_error(name: &str) -> Self {
+    pub fn new_name_error(name: &str, span: Span) -> Self {
         Self::NameError {
             name: name.to_owned(),
+            span,
         }
     }
 }
@@ -33,16 +56,155 @@ impl Display for InterpreterError {
             InterpreterError::TypeError {
                 wanted_type,
                 given_type,
+                span,
             } => write!(
                 f,
-                "TypeError: type needed : {}, bot got type {}",
-                wanted_type, given_type
+                "TypeError at {}: type needed : {}, bot got type {}",
+                span, wanted_type, given_type
+            ),
+            InterpreterError::NameError { name, span } => write!(
+                f,
+                "Unknown name {:?} at {} : this name cannot be bound to a value because it is unknown",
+                name, span
+            ),
+            InterpreterError::InvalidExpression { span } => write!(
+                f,
+                "Invalid expression at {} : this expression contains syntax errors",
+                span
             ),
-            InterpreterError::NameError { name } => write!(
+            InterpreterError::ResourceExhausted { limit, span } => write!(
                 f,
-                "Unknown name {:?} : this name cannot be bound to a value because it is unknown",
-                name
+                "Resource exhausted at {} : the evaluation went over the limit of {}",
+                span, limit
             ),
         }
     }
 }
+
+impl ToDiagnostic for InterpreterError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            InterpreterError::TypeError {
+                wanted_type,
+                given_type,
+                span,
+            } => {
+                let diagnostic = Diagnostic::new("type error").with_label(
+                    *span,
+                    format!("expected a {}, found a {}", wanted_type, given_type),
+                );
+                match wanted_type.as_str() {
+                    "Closure" => {
+                        diagnostic.with_help("only functions can be applied to an argument")
+                    }
+                    "Boolean" => diagnostic
+                        .with_help("booleans are `true`, `false` and the results of comparisons"),
+                    _ => diagnostic,
+                }
+            }
+            InterpreterError::NameError { name, span } => {
+                Diagnostic::new(format!("unknown name `{}`", name))
+                    .with_label(*span, "this name is not bound to any value")
+                    .with_help(format!(
+                        "names are bound by abstractions, for example `\\{}. ...`",
+                        name
+                    ))
+            }
+            InterpreterError::InvalidExpression { span } => {
+                Diagnostic::new("cannot evaluate an invalid expression")
+                    .with_label(*span, "this expression contains syntax errors")
+            }
+            InterpreterError::ResourceExhausted { limit, span } => {
+                let option = match limit {
+                    Limit::Steps(_) => "--max-steps",
+                    Limit::Time(_) => "--timeout",
+                };
+                Diagnostic::new(format!("evaluation stopped after {}", limit))
+                    .with_label(*span, "this expression was being evaluated")
+                    .with_help(format!(
+                        "the program may not terminate, or it needs a higher `{}`",
+                        option
+                    ))
+            }
+        }
+    }
+}
+
+/// Any error that can happen while running a program, from lexing to evaluation.
+#[derive(Debug, PartialEq)]
+pub enum ExecutionError {
+    Lexer(LexerError),
+    Syntax(Vec<SyntaxError>),
+    /// The names found unbound by `check::unbound_names`, before evaluation.
+    Check(Vec<InterpreterError>),
+    Type(TypeError),
+    Interpreter(InterpreterError),
+}
+
+impl From<LexerError> for ExecutionError {
+    fn from(error: LexerError) -> Self {
+        Self::Lexer(error)
+    }
+}
+
+impl From<Vec<SyntaxError>> for ExecutionError {
+    fn from(errors: Vec<SyntaxError>) -> Self {
+        Self::Syntax(errors)
+    }
+}
+
+impl From<Vec<InterpreterError>> for ExecutionError {
+    fn from(errors: Vec<InterpreterError>) -> Self {
+        Self::Check(errors)
+    }
+}
+
+impl From<TypeError> for ExecutionError {
+    fn from(error: TypeError) -> Self {
+        Self::Type(error)
+    }
+}
+
+impl From<InterpreterError> for ExecutionError {
+    fn from(error: InterpreterError) -> Self {
+        Self::Interpreter(error)
+    }
+}
+
+impl Error for ExecutionError {}
+
+impl Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionError::Lexer(error) => write!(f, "{}", error),
+            ExecutionError::Syntax(errors) => {
+                let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            ExecutionError::Check(errors) => {
+                let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            ExecutionError::Type(error) => write!(f, "{}", error),
+            ExecutionError::Interpreter(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl ExecutionError {
+    /// The diagnostics to report to the user, there can be many syntax or name
+    /// errors at once.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            ExecutionError::Lexer(error) => vec![error.to_diagnostic()],
+            ExecutionError::Syntax(errors) => {
+                errors.iter().map(|error| error.to_diagnostic()).collect()
+            }
+            ExecutionError::Check(errors) => {
+                errors.iter().map(|error| error.to_diagnostic()).collect()
+            }
+            ExecutionError::Type(error) => vec![error.to_diagnostic()],
+            ExecutionError::Interpreter(error) => vec![error.to_diagnostic()],
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use crate::{interpreter::error::InterpreterError, span::Span};
+
+/// Bounds on the resources an evaluation can use, no bound by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Maximum number of expressions evaluated.
+    pub max_steps: Option<u64>,
+    /// Maximum duration of the evaluation.
+    pub timeout: Option<Duration>,
+}
+
+/// The limit reached by an evaluation that was stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Steps(u64),
+    Time(Duration),
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps(steps) => write!(f, "{} steps", steps),
+            Limit::Time(duration) => write!(f, "{:?}", duration),
+        }
+    }
+}
+
+/// How often the clock is read, reading it at every step would slow down evaluation.
+const STEPS_BETWEEN_CLOCK_CHECKS: u64 = 1024;
+
+/// The resources left to an evaluation, consumed one step per evaluated expression.
+#[derive(Debug)]
+pub struct Budget {
+    limits: Limits,
+    steps: u64,
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    /// Starts counting, the timeout runs from now.
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            steps: 0,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(Limits::default())
+    }
+
+    /// Consumes one step to evaluate the expression at `span`.
+    pub fn step(&mut self, span: Span) -> Result<(), InterpreterError> {
+        self.steps += 1;
+        if let Some(max_steps) = self.limits.max_steps {
+            if self.steps > max_steps {
+                return Err(InterpreterError::ResourceExhausted {
+                    limit: Limit::Steps(max_steps),
+                    span,
+                });
+            }
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout) {
+            if self.steps.is_multiple_of(STEPS_BETWEEN_CLOCK_CHECKS) && Instant::now() > deadline {
+                return Err(InterpreterError::ResourceExhausted {
+                    limit: Limit::Time(timeout),
+                    span,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        interpreter::{error::InterpreterError, limits::*, Interpreter},
+        lexer::lexer,
+        parser::parse,
+    };
+
+    const OMEGA: &str = r"(\x.x x) (\x.x x)";
+
+    #[test]
+    fn test_step_limit() {
+        let expr = parse(&lexer(OMEGA).unwrap()).unwrap();
+        let limits = Limits {
+            max_steps: Some(100),
+            timeout: None,
+        };
+        let result = expr.eval_machine_with_limits(limits);
+        assert!(matches!(
+            result,
+            Err(InterpreterError::ResourceExhausted {
+                limit: Limit::Steps(100),
+                ..
+            })
+        ));
+        assert_eq!(expr.eval_with_limits(limits), result);
+    }
+
+    #[test]
+    fn test_enough_steps() {
+        let expr = parse(&lexer(r"(\x.x+1) 2").unwrap()).unwrap();
+        let limits = Limits {
+            max_steps: Some(6),
+            timeout: None,
+        };
+        assert!(expr.eval_with_limits(limits).is_ok());
+        assert!(expr.eval_machine_with_limits(limits).is_ok());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut interpreter = Interpreter::new().with_limits(Limits {
+            max_steps: None,
+            timeout: Some(Duration::from_millis(50)),
+        });
+        assert!(matches!(
+            interpreter.execute(OMEGA),
+            Err(crate::interpreter::error::ExecutionError::Interpreter(
+                InterpreterError::ResourceExhausted {
+                    limit: Limit::Time(_),
+                    ..
+                }
+            ))
+        ));
+    }
+}
@@ -0,0 +1,399 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::{ArithmeticOp, ComparisonOp, Expr, ExprKind, LogicalOp, UnaryOp},
+    interpreter::{
+        call, condition,
+        env::{Binding, Env, Thunk},
+        error::InterpreterError,
+        limits::{Budget, Limits},
+        numbers, recursive_closure, EvalResult, Strategy,
+    },
+    span::Span,
+};
+
+/// A binary operator waiting for its operands.
+enum Operation {
+    Arithmetic(ArithmeticOp),
+    Comparison(ComparisonOp),
+}
+
+/// What remains to be done with the value being computed.
+enum Frame {
+    /// The argument of an application is known, the function is evaluated next.
+    Function { function: Rc<Expr>, env: Env },
+    /// The function is being evaluated, it is then applied to `argument`.
+    Apply {
+        argument: Binding,
+        function_span: Span,
+    },
+    /// The left operand is being evaluated, `rhs` is evaluated next.
+    Rhs {
+        operation: Operation,
+        lhs_span: Span,
+        rhs: Rc<Expr>,
+        env: Env,
+    },
+    /// The right operand is being evaluated, `operation` is then applied.
+    Binary {
+        operation: Operation,
+        lhs: EvalResult,
+        lhs_span: Span,
+        rhs_span: Span,
+    },
+    /// The left operand of `operation` is being evaluated, `rhs` is evaluated
+    /// next unless the left operand decides the result.
+    Logical {
+        operation: LogicalOp,
+        lhs_span: Span,
+        rhs: Rc<Expr>,
+        env: Env,
+    },
+    /// The right operand of a logical operator is being evaluated, it is its result.
+    LogicalRhs { rhs_span: Span },
+    /// The operand of `operation` is being evaluated, `operation` is then applied.
+    Unary {
+        operation: UnaryOp,
+        operand_span: Span,
+    },
+    /// The condition is being evaluated, one of the branches is evaluated next.
+    Branch {
+        condition_span: Span,
+        true_branch: Rc<Expr>,
+        false_branch: Rc<Expr>,
+        env: Env,
+    },
+    /// The value of a `let rec` is being evaluated, `body` is evaluated next.
+    LetRec {
+        name: Rc<str>,
+        value_span: Span,
+        body: Rc<Expr>,
+        env: Env,
+    },
+    /// A thunk is being evaluated, its value is then memoized.
+    Update { thunk: Rc<Thunk> },
+}
+
+enum State {
+    Eval(Rc<Expr>, Env),
+    Return(EvalResult),
+}
+
+/// Evaluates `expr` with an abstract machine whose continuation is a stack of
+/// frames on the heap, so that the depth of the evaluation is not limited by
+/// the native stack.
+///
+/// Calls in tail position, such as the body of a function or the branches of
+/// a conditional, don't push any frame, so tail recursive functions run in
+/// constant space. Sub-expressions are evaluated in the same order as
+/// `Expr::eval`, which gives the same results and errors. A step of `budget`
+/// is consumed for each evaluated expression, like `Expr::eval` does.
+pub fn run(
+    expr: &Expr,
+    env: &Env,
+    strategy: Strategy,
+    budget: &mut Budget,
+) -> Result<EvalResult, InterpreterError> {
+    let mut stack: Vec<Frame> = vec![];
+    let mut state = State::Eval(Rc::new(expr.clone()), env.clone());
+    loop {
+        state = match state {
+            State::Eval(expr, env) => {
+                budget.step(expr.span)?;
+                eval(&expr, env, strategy, &mut stack)?
+            }
+            State::Return(value) => match stack.pop() {
+                None => return Ok(value),
+                Some(frame) => resume(frame, value, &mut stack)?,
+            },
+        };
+    }
+}
+
+/// Starts the evaluation of `expr`, pushing the frames needed to finish it.
+fn eval(
+    expr: &Expr,
+    env: Env,
+    strategy: Strategy,
+    stack: &mut Vec<Frame>,
+) -> Result<State, InterpreterError> {
+    let state = match &expr.kind {
+        ExprKind::Abs { var, body, .. } => State::Return(EvalResult::Closure {
+            var: Rc::clone(var),
+            body: Rc::clone(body),
+            context: env,
+            self_name: None,
+        }),
+        ExprKind::App {
+            function,
+            parameter,
+        } => match strategy.delay(parameter, &env) {
+            Some(thunk) => {
+                stack.push(Frame::Apply {
+                    argument: thunk,
+                    function_span: function.span,
+                });
+                State::Eval(Rc::clone(function), env)
+            }
+            None => {
+                stack.push(Frame::Function {
+                    function: Rc::clone(function),
+                    env: env.clone(),
+                });
+                State::Eval(Rc::clone(parameter), env)
+            }
+        },
+        ExprKind::Arithmetic {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            stack.push(Frame::Rhs {
+                operation: Operation::Arithmetic(operation.clone()),
+                lhs_span: lhs.span,
+                rhs: Rc::clone(rhs),
+                env: env.clone(),
+            });
+            State::Eval(Rc::clone(lhs), env)
+        }
+        ExprKind::Comparison {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            stack.push(Frame::Rhs {
+                operation: Operation::Comparison(operation.clone()),
+                lhs_span: lhs.span,
+                rhs: Rc::clone(rhs),
+                env: env.clone(),
+            });
+            State::Eval(Rc::clone(lhs), env)
+        }
+        ExprKind::Logical {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            stack.push(Frame::Logical {
+                operation: operation.clone(),
+                lhs_span: lhs.span,
+                rhs: Rc::clone(rhs),
+                env: env.clone(),
+            });
+            State::Eval(Rc::clone(lhs), env)
+        }
+        ExprKind::Unary { operation, operand } => {
+            stack.push(Frame::Unary {
+                operation: operation.clone(),
+                operand_span: operand.span,
+            });
+            State::Eval(Rc::clone(operand), env)
+        }
+        ExprKind::Conditional {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            stack.push(Frame::Branch {
+                condition_span: condition.span,
+                true_branch: Rc::clone(true_branch),
+                false_branch: Rc::clone(false_branch),
+                env: env.clone(),
+            });
+            State::Eval(Rc::clone(condition), env)
+        }
+        ExprKind::LetRec {
+            name, value, body, ..
+        } => {
+            stack.push(Frame::LetRec {
+                name: Rc::clone(name),
+                value_span: value.span,
+                body: Rc::clone(body),
+                env: env.clone(),
+            });
+            State::Eval(Rc::clone(value), env)
+        }
+        ExprKind::Var { name } => match env.get(name) {
+            Some(Binding::Value(value)) => State::Return(value.clone()),
+            Some(Binding::Thunk(thunk)) => match thunk.value() {
+                Some(value) => State::Return(value),
+                None => {
+                    if thunk.memoize() {
+                        stack.push(Frame::Update {
+                            thunk: Rc::clone(thunk),
+                        });
+                    }
+                    State::Eval(Rc::clone(&thunk.expr), thunk.env.clone())
+                }
+            },
+            None => return Err(InterpreterError::new_name_error(name, expr.span)),
+        },
+        ExprKind::NumericLiteral { value } => State::Return(EvalResult::Value(*value)),
+        ExprKind::BooleanLiteral { value } => State::Return(EvalResult::Boolean(*value)),
+        ExprKind::Error => return Err(InterpreterError::InvalidExpression { span: expr.span }),
+    };
+    Ok(state)
+}
+
+/// Continues the evaluation suspended in `frame` now that `value` is known.
+fn resume(
+    frame: Frame,
+    value: EvalResult,
+    stack: &mut Vec<Frame>,
+) -> Result<State, InterpreterError> {
+    let state = match frame {
+        Frame::Function { function, env } => {
+            stack.push(Frame::Apply {
+                argument: value.into(),
+                function_span: function.span,
+            });
+            State::Eval(function, env)
+        }
+        Frame::Apply {
+            argument,
+            function_span,
+        } => {
+            let (body, context) = call(value, argument, function_span)?;
+            State::Eval(body, context)
+        }
+        Frame::Rhs {
+            operation,
+            lhs_span,
+            rhs,
+            env,
+        } => {
+            stack.push(Frame::Binary {
+                operation,
+                lhs: value,
+                lhs_span,
+                rhs_span: rhs.span,
+            });
+            State::Eval(rhs, env)
+        }
+        Frame::Binary {
+            operation,
+            lhs,
+            lhs_span,
+            rhs_span,
+        } => {
+            let (x, y) = numbers(lhs, value, lhs_span, rhs_span)?;
+            State::Return(match operation {
+                Operation::Arithmetic(operation) => EvalResult::Value(operation.eval(x, y)),
+                Operation::Comparison(operation) => EvalResult::Boolean(operation.eval(x, y)),
+            })
+        }
+        Frame::Logical {
+            operation,
+            lhs_span,
+            rhs,
+            env,
+        } => match operation.short_circuit(condition(value, lhs_span)?) {
+            Some(result) => State::Return(EvalResult::Boolean(result)),
+            None => {
+                stack.push(Frame::LogicalRhs { rhs_span: rhs.span });
+                State::Eval(rhs, env)
+            }
+        },
+        Frame::LogicalRhs { rhs_span } => {
+            State::Return(EvalResult::Boolean(condition(value, rhs_span)?))
+        }
+        Frame::Unary {
+            operation,
+            operand_span,
+        } => State::Return(operation.eval(value, operand_span)?),
+        Frame::Branch {
+            condition_span,
+            true_branch,
+            false_branch,
+            env,
+        } => {
+            if condition(value, condition_span)? {
+                State::Eval(true_branch, env)
+            } else {
+                State::Eval(false_branch, env)
+            }
+        }
+        Frame::LetRec {
+            name,
+            value_span,
+            body,
+            env,
+        } => {
+            let value = recursive_closure(value, &name, value_span)?;
+            State::Eval(body, env.bind(name, value))
+        }
+        Frame::Update { thunk } => {
+            thunk.update(&value);
+            State::Return(value)
+        }
+    };
+    Ok(state)
+}
+
+impl Expr {
+    /// Evaluates the expression like `Expr::eval`, without using the native stack.
+    pub fn eval_machine(&self) -> Result<EvalResult, InterpreterError> {
+        self.eval_machine_with_limits(Limits::default())
+    }
+
+    /// Evaluates the expression like `Expr::eval_with_limits`, without using the native stack.
+    pub fn eval_machine_with_limits(&self, limits: Limits) -> Result<EvalResult, InterpreterError> {
+        self.eval_machine_with(Strategy::default(), limits)
+    }
+
+    /// Evaluates the expression like `Expr::eval_with`, without using the native stack.
+    pub fn eval_machine_with(
+        &self,
+        strategy: Strategy,
+        limits: Limits,
+    ) -> Result<EvalResult, InterpreterError> {
+        run(self, &Env::new(), strategy, &mut Budget::new(limits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interpreter::{execute, EvalResult, Evaluator, Interpreter},
+        lexer::lexer,
+        parser::parse,
+    };
+
+    const Y: &str = r"(\f.(\x.f (\v.x x v)) (\x.f (\v.x x v)))";
+
+    #[test]
+    fn test_deep_recursion() {
+        let countdown = format!(r"{Y} (\f.\n.n=0 ? 0 : f (n-1)) 100000");
+        assert_eq!(execute(&countdown), Ok(vec![EvalResult::Value(0.)]));
+
+        let sum = r"let rec sum = \n.n=0 ? 0 : n + (sum n-1) in sum 100000";
+        assert_eq!(execute(sum), Ok(vec![EvalResult::Value(5000050000.)]));
+    }
+
+    #[test]
+    fn test_same_errors() {
+        for program in [
+            r"(\x.x+y) 1",
+            r"1+1 2",
+            r"(\x.x) true + 1",
+            r"(1 = 1) + (2 3)",
+            r"-(1 > 2)",
+            r"true && 1",
+            r"false || (\x.x)",
+            r"!(\x.x)",
+        ] {
+            let expr = parse(&lexer(program).unwrap()).unwrap();
+            assert!(expr.eval().is_err());
+            assert_eq!(expr.eval_machine(), expr.eval(), "{}", program);
+        }
+    }
+
+    #[test]
+    fn test_recursive_evaluator() {
+        let mut interpreter = Interpreter::new().with_evaluator(Evaluator::Recursive);
+        assert_eq!(
+            interpreter.execute(r"(\x.\y.x+y) 1 2"),
+            Ok(vec![EvalResult::Value(3.)])
+        );
+    }
+}
@@ -1,13 +1,24 @@
+pub mod check;
+pub mod env;
 pub mod error;
+pub mod limits;
+pub mod machine;
+pub mod reduce;
 
 use crate::{
-    ast::{ArithmeticOp, ComparisonOp, Expr},
-    interpreter::error::InterpreterError,
+    ast::{ArithmeticOp, ComparisonOp, Expr, ExprKind, Item, LogicalOp, Program, UnaryOp},
+    interpreter::{
+        env::{Binding, Env, Thunk},
+        error::{ExecutionError, InterpreterError},
+        limits::{Budget, Limits},
+    },
     lexer::lexer,
-    parser::parse,
+    parser::parse_program,
+    printer::Printer,
+    span::Span,
 };
 
-use std::{collections::HashMap, error::Error, fmt::Display, rc::Rc};
+use std::{fmt::Display, rc::Rc};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum EvalResult {
@@ -17,15 +28,138 @@ pub enum EvalResult {
         var: Rc<str>,
         body: Rc<Expr>,
         context: Env,
+        /// Name under which a closure made by `let rec` refers to itself.
+        self_name: Option<Rc<str>>,
     },
 }
 
+/// How functions are shown to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClosureStyle {
+    /// As their source, where captured variables are replaced by their value: `\y.2 + y`.
+    #[default]
+    Substituted,
+    /// As their source along with the value of captured variables:
+    /// `<closure \y.x + y where x = 2>`.
+    Environment,
+}
+
+/// Shows functions in the `ClosureStyle::Substituted` style.
 impl Display for EvalResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.show(ClosureStyle::default(), Printer::new()))
+    }
+}
+
+/// The source of a closure, written `let rec name = ... in name` if it refers to itself.
+fn closure_source(var: &Rc<str>, body: &Rc<Expr>, self_name: &Option<Rc<str>>) -> Rc<Expr> {
+    let function = Expr::new(ExprKind::Abs {
+        var: Rc::clone(var),
+        ty: None,
+        body: Rc::clone(body),
+    });
+    Rc::new(match self_name {
+        Some(name) => Expr::let_rec(name, function, Expr::var(name)),
+        None => function,
+    })
+}
+
+/// The free variables of `source` bound in `context`, sorted by name.
+fn captured<'a>(source: &Expr, context: &'a Env) -> Vec<(Rc<str>, &'a Binding)> {
+    let mut captured: Vec<_> = source
+        .free_vars()
+        .into_iter()
+        .filter_map(|name| context.get(&name).map(|binding| (name, binding)))
+        .collect();
+    captured.sort_by(|(a, _), (b, _)| a.cmp(b));
+    captured
+}
+
+/// `source` where the variables bound in `context` are replaced by their value.
+fn substitute_context(source: &Rc<Expr>, context: &Env) -> Expr {
+    let mut expr = Rc::clone(source);
+    for (name, binding) in captured(source, context) {
+        expr = reduce::beta(&name, &expr, &Rc::new(binding_expr(binding)));
+    }
+    Rc::unwrap_or_clone(expr)
+}
+
+/// The expression a name is bound to, the expression of a thunk that was not
+/// evaluated yet.
+fn binding_expr(binding: &Binding) -> Expr {
+    match binding {
+        Binding::Value(value) => value.to_expr(),
+        Binding::Thunk(thunk) => match thunk.value() {
+            Some(value) => value.to_expr(),
+            None => substitute_context(&thunk.expr, &thunk.env),
+        },
+    }
+}
+
+impl EvalResult {
+    /// The expression standing for the value, functions being their source where
+    /// captured variables are replaced by their value.
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            EvalResult::Value(value) => Expr::numeric_literal(*value),
+            EvalResult::Boolean(value) => Expr::boolean_literal(*value),
+            EvalResult::Closure {
+                var,
+                body,
+                context,
+                self_name,
+            } => substitute_context(&closure_source(var, body, self_name), context),
+        }
+    }
+
+    /// Writes the value for the user, showing functions in the given `style`
+    /// with `printer`.
+    pub fn show(&self, style: ClosureStyle, printer: Printer) -> String {
+        match (self, style) {
+            (
+                EvalResult::Closure {
+                    var,
+                    body,
+                    context,
+                    self_name,
+                },
+                ClosureStyle::Environment,
+            ) => {
+                let source = closure_source(var, body, self_name);
+                let captured: Vec<String> = captured(&source, context)
+                    .into_iter()
+                    .map(|(name, binding)| {
+                        let value = match binding {
+                            Binding::Value(value) => value.show(style, printer),
+                            Binding::Thunk(thunk) => match thunk.value() {
+                                Some(value) => value.show(style, printer),
+                                None => printer.print(&binding_expr(binding)),
+                            },
+                        };
+                        format!("{} = {}", name, value)
+                    })
+                    .collect();
+                let source = printer.print(&source);
+                if captured.is_empty() {
+                    format!("<closure {}>", source)
+                } else {
+                    format!("<closure {} where {}>", source, captured.join(", "))
+                }
+            }
+            (EvalResult::Closure { .. }, ClosureStyle::Substituted) => {
+                printer.print(&self.to_expr())
+            }
+            (EvalResult::Value(value), _) => printer.print(&Expr::numeric_literal(*value)),
+            (EvalResult::Boolean(value), _) => value.to_string(),
+        }
+    }
+
+    /// The name of the type of the value, as shown in type errors.
+    pub fn type_name(&self) -> &'static str {
         match self {
-            EvalResult::Value(val) => write!(f, "{}", val),
-            EvalResult::Boolean(val) => write!(f, "{}", val),
-            EvalResult::Closure { .. } => write!(f, "Closure"),
+            EvalResult::Value(_) => "Value",
+            EvalResult::Boolean(_) => "Boolean",
+            EvalResult::Closure { .. } => "Closure",
         }
     }
 }
@@ -54,109 +188,407 @@ impl ComparisonOp {
     }
 }
 
-type Env = HashMap<Rc<str>, EvalResult>;
+impl LogicalOp {
+    /// The result of the operation when its left operand is `lhs`, if the right
+    /// operand doesn't matter.
+    pub fn short_circuit(&self, lhs: bool) -> Option<bool> {
+        match (self, lhs) {
+            (LogicalOp::And, false) => Some(false),
+            (LogicalOp::Or, true) => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl UnaryOp {
+    /// Applies the operator to `operand`, the value of the expression at `span`.
+    pub fn eval(&self, operand: EvalResult, span: Span) -> Result<EvalResult, InterpreterError> {
+        match (self, operand) {
+            (UnaryOp::Neg, EvalResult::Value(x)) => Ok(EvalResult::Value(-x)),
+            (UnaryOp::Not, EvalResult::Boolean(b)) => Ok(EvalResult::Boolean(!b)),
+            (UnaryOp::Neg, other) => Err(InterpreterError::new_type_error(
+                "Value",
+                other.type_name(),
+                span,
+            )),
+            (UnaryOp::Not, other) => Err(InterpreterError::new_type_error(
+                "Boolean",
+                other.type_name(),
+                span,
+            )),
+        }
+    }
+}
+
+/// Checks that both operands of a binary operator are numbers.
+fn numbers(
+    lhs: EvalResult,
+    rhs: EvalResult,
+    lhs_span: Span,
+    rhs_span: Span,
+) -> Result<(f64, f64), InterpreterError> {
+    match (lhs, rhs) {
+        (EvalResult::Value(lhs), EvalResult::Value(rhs)) => Ok((lhs, rhs)),
+        (EvalResult::Value(_), other) => Err(InterpreterError::new_type_error(
+            "Value",
+            other.type_name(),
+            rhs_span,
+        )),
+        (other, _) => Err(InterpreterError::new_type_error(
+            "Value",
+            other.type_name(),
+            lhs_span,
+        )),
+    }
+}
+
+/// Checks that the condition of a conditional, or an operand of a logical
+/// operator, is a boolean.
+fn condition(value: EvalResult, span: Span) -> Result<bool, InterpreterError> {
+    match value {
+        EvalResult::Boolean(cond) => Ok(cond),
+        other => Err(InterpreterError::new_type_error(
+            "Boolean",
+            other.type_name(),
+            span,
+        )),
+    }
+}
+
+/// Applies `closure` to `argument`, returning the body to evaluate and its environment.
+fn call(
+    closure: EvalResult,
+    argument: Binding,
+    function_span: Span,
+) -> Result<(Rc<Expr>, Env), InterpreterError> {
+    match &closure {
+        EvalResult::Closure {
+            var,
+            body,
+            context,
+            self_name,
+        } => {
+            let context = match self_name {
+                Some(name) => context.bind(Rc::clone(name), closure.clone()),
+                None => context.clone(),
+            };
+            Ok((Rc::clone(body), context.bind(Rc::clone(var), argument)))
+        }
+        other => Err(InterpreterError::new_type_error(
+            "Closure",
+            other.type_name(),
+            function_span,
+        )),
+    }
+}
+
+/// Turns the value of a `let rec` into a closure able to refer to itself as `name`.
+fn recursive_closure(
+    value: EvalResult,
+    name: &Rc<str>,
+    span: Span,
+) -> Result<EvalResult, InterpreterError> {
+    match value {
+        EvalResult::Closure {
+            var, body, context, ..
+        } => Ok(EvalResult::Closure {
+            var,
+            body,
+            context,
+            self_name: Some(Rc::clone(name)),
+        }),
+        other => Err(InterpreterError::new_type_error(
+            "Closure",
+            other.type_name(),
+            span,
+        )),
+    }
+}
+
+/// When the argument of an application is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Strategy {
+    /// Before calling the function, even if the function doesn't use it.
+    #[default]
+    CallByValue,
+    /// Each time the function uses it, if it ever does.
+    CallByName,
+    /// The first time the function uses it, its value is then reused.
+    CallByNeed,
+}
+
+impl Strategy {
+    /// Delays the evaluation of `expr` in `env`, unless arguments are evaluated eagerly.
+    fn delay(self, expr: &Rc<Expr>, env: &Env) -> Option<Binding> {
+        let memoize = match self {
+            Strategy::CallByValue => return None,
+            Strategy::CallByName => false,
+            Strategy::CallByNeed => true,
+        };
+        Some(Binding::Thunk(Thunk::new(
+            Rc::clone(expr),
+            env.clone(),
+            memoize,
+        )))
+    }
+}
+
+/// The value of `binding`, evaluating it if it is a thunk.
+fn force(
+    binding: &Binding,
+    strategy: Strategy,
+    budget: &mut Budget,
+) -> Result<EvalResult, InterpreterError> {
+    match binding {
+        Binding::Value(value) => Ok(value.clone()),
+        Binding::Thunk(thunk) => match thunk.value() {
+            Some(value) => Ok(value),
+            None => {
+                let value = thunk.expr.eval_rec(&thunk.env, strategy, budget)?;
+                thunk.update(&value);
+                Ok(value)
+            }
+        },
+    }
+}
 
 impl Expr {
     pub fn eval(&self) -> Result<EvalResult, InterpreterError> {
-        self.eval_rec(&HashMap::new())
+        self.eval_with_limits(Limits::default())
     }
 
-    fn eval_rec(&self, env: &Env) -> Result<EvalResult, InterpreterError> {
-        match self {
-            Expr::Abs { var, body } => Ok(EvalResult::Closure {
+    /// Evaluates the expression, failing with `InterpreterError::ResourceExhausted`
+    /// when going over `limits`.
+    pub fn eval_with_limits(&self, limits: Limits) -> Result<EvalResult, InterpreterError> {
+        self.eval_with(Strategy::default(), limits)
+    }
+
+    /// Evaluates the expression, passing arguments to functions according to `strategy`.
+    pub fn eval_with(
+        &self,
+        strategy: Strategy,
+        limits: Limits,
+    ) -> Result<EvalResult, InterpreterError> {
+        self.eval_rec(&Env::new(), strategy, &mut Budget::new(limits))
+    }
+
+    fn eval_rec(
+        &self,
+        env: &Env,
+        strategy: Strategy,
+        budget: &mut Budget,
+    ) -> Result<EvalResult, InterpreterError> {
+        budget.step(self.span)?;
+        match &self.kind {
+            ExprKind::Abs { var, body, .. } => Ok(EvalResult::Closure {
                 body: Rc::clone(body),
                 var: var.clone(),
                 context: env.clone(),
+                self_name: None,
             }),
-            Expr::App {
+            ExprKind::App {
                 function,
                 parameter,
             } => {
-                let parameter = parameter.eval_rec(env)?;
-                let function = function.eval_rec(env)?;
-                match function {
-                    EvalResult::Closure {
-                        var,
-                        body,
-                        mut context,
-                    } => {
-                        context.insert(var, parameter);
-                        body.eval_rec(&context)
-                    }
-                    other => Err(InterpreterError::new_type_error(
-                        "Closure",
-                        &format!("{:?}", other),
-                    )),
-                }
+                let parameter = match strategy.delay(parameter, env) {
+                    Some(thunk) => thunk,
+                    None => parameter.eval_rec(env, strategy, budget)?.into(),
+                };
+                let (body, context) = call(
+                    function.eval_rec(env, strategy, budget)?,
+                    parameter,
+                    function.span,
+                )?;
+                body.eval_rec(&context, strategy, budget)
             }
-            Expr::Arithmetic {
+            ExprKind::Arithmetic {
                 operation,
                 lhs,
                 rhs,
             } => {
-                let lhs = lhs.eval_rec(env)?;
-                let rhs = rhs.eval_rec(env)?;
-                match (lhs, rhs) {
-                    (EvalResult::Value(lhs), EvalResult::Value(rhs)) => {
-                        Ok(EvalResult::Value(operation.eval(lhs, rhs)))
-                    }
-                    other => Err(InterpreterError::new_type_error(
-                        "Value",
-                        &format!("{:?}", other),
-                    )),
-                }
+                let (x, y) = numbers(
+                    lhs.eval_rec(env, strategy, budget)?,
+                    rhs.eval_rec(env, strategy, budget)?,
+                    lhs.span,
+                    rhs.span,
+                )?;
+                Ok(EvalResult::Value(operation.eval(x, y)))
             }
-            Expr::Var { name } => env
-                .get(name)
-                .cloned()
-                .ok_or(InterpreterError::new_name_error(name)),
-            Expr::NumericLiteral { value } => Ok(EvalResult::Value(*value)),
-            Expr::Conditional {
-                condition,
+            ExprKind::Var { name } => match env.get(name) {
+                Some(binding) => force(binding, strategy, budget),
+                None => Err(InterpreterError::new_name_error(name, self.span)),
+            },
+            ExprKind::NumericLiteral { value } => Ok(EvalResult::Value(*value)),
+            ExprKind::Conditional {
+                condition: cond,
                 true_branch,
                 false_branch,
-            } => match condition.eval_rec(env)? {
-                EvalResult::Boolean(cond) => {
-                    if cond {
-                        true_branch.eval_rec(env)
-                    } else {
-                        false_branch.eval_rec(env)
-                    }
+            } => {
+                if condition(cond.eval_rec(env, strategy, budget)?, cond.span)? {
+                    true_branch.eval_rec(env, strategy, budget)
+                } else {
+                    false_branch.eval_rec(env, strategy, budget)
                 }
-                other => Err(InterpreterError::new_type_error(
-                    "Boolean",
-                    &format!("{:?}", other),
-                )),
-            },
-            Expr::Comparison {
+            }
+            ExprKind::Comparison {
+                operation,
+                lhs,
+                rhs,
+            } => {
+                let (x, y) = numbers(
+                    lhs.eval_rec(env, strategy, budget)?,
+                    rhs.eval_rec(env, strategy, budget)?,
+                    lhs.span,
+                    rhs.span,
+                )?;
+                Ok(EvalResult::Boolean(operation.eval(x, y)))
+            }
+            ExprKind::Logical {
                 operation,
                 lhs,
                 rhs,
-            } => match (lhs.eval_rec(env)?, rhs.eval_rec(env)?) {
-                (EvalResult::Value(lhs), EvalResult::Value(rhs)) => {
-                    Ok(EvalResult::Boolean(operation.eval(lhs, rhs)))
+            } => {
+                let lhs = condition(lhs.eval_rec(env, strategy, budget)?, lhs.span)?;
+                match operation.short_circuit(lhs) {
+                    Some(result) => Ok(EvalResult::Boolean(result)),
+                    None => {
+                        let rhs = condition(rhs.eval_rec(env, strategy, budget)?, rhs.span)?;
+                        Ok(EvalResult::Boolean(rhs))
+                    }
                 }
-                (EvalResult::Value(_), other) | (other, _) => {
-                    // dbg!(env);
-                    Err(InterpreterError::new_type_error(
-                        "Value",
-                        &format!("{:?}", other),
-                    ))
+            }
+            ExprKind::BooleanLiteral { value } => Ok(EvalResult::Boolean(*value)),
+            ExprKind::Unary { operation, operand } => {
+                operation.eval(operand.eval_rec(env, strategy, budget)?, operand.span)
+            }
+            ExprKind::LetRec {
+                name, value, body, ..
+            } => {
+                let value =
+                    recursive_closure(value.eval_rec(env, strategy, budget)?, name, value.span)?;
+                body.eval_rec(&env.bind(Rc::clone(name), value), strategy, budget)
+            }
+            ExprKind::Error => Err(InterpreterError::InvalidExpression { span: self.span }),
+        }
+    }
+}
+
+/// The algorithm used to evaluate expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Evaluator {
+    /// `Expr::eval`, which recurses on the native stack.
+    Recursive,
+    /// `machine::run`, which can evaluate arbitrarily deep recursions.
+    #[default]
+    Machine,
+}
+
+/// Runs programs one after the other, keeping the definitions made by the previous ones.
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    env: Env,
+    evaluator: Evaluator,
+    strategy: Strategy,
+    limits: Limits,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_evaluator(self, evaluator: Evaluator) -> Self {
+        Self { evaluator, ..self }
+    }
+
+    pub fn with_strategy(self, strategy: Strategy) -> Self {
+        Self { strategy, ..self }
+    }
+
+    /// Bounds the resources used by each call to `run`.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    fn eval(&self, expr: &Expr, budget: &mut Budget) -> Result<EvalResult, InterpreterError> {
+        match self.evaluator {
+            Evaluator::Recursive => expr.eval_rec(&self.env, self.strategy, budget),
+            Evaluator::Machine => machine::run(expr, &self.env, self.strategy, budget),
+        }
+    }
+
+    /// Evaluates the items of `program` in order and returns the value of each expression.
+    ///
+    /// If an item fails, none of the definitions of `program` are kept. Unless
+    /// arguments are evaluated by value, definitions are only evaluated when
+    /// they are used.
+    pub fn run(&mut self, program: &Program) -> Result<Vec<EvalResult>, InterpreterError> {
+        let env = self.env.clone();
+        let results = self.run_items(program);
+        if results.is_err() {
+            self.env = env;
+        }
+        results
+    }
+
+    fn run_items(&mut self, program: &Program) -> Result<Vec<EvalResult>, InterpreterError> {
+        let mut budget = Budget::new(self.limits);
+        let mut results = vec![];
+        for item in &program.items {
+            match item {
+                Item::Definition { name, value } => {
+                    let value = match self.strategy.delay(&Rc::new(value.clone()), &self.env) {
+                        Some(thunk) => thunk,
+                        None => self.eval(value, &mut budget)?.into(),
+                    };
+                    self.env = self.env.bind(Rc::clone(name), value);
                 }
-            },
-            Expr::BooleanLiteral { value } => Ok(EvalResult::Boolean(*value)),
+                Item::Expression(expr) => results.push(self.eval(expr, &mut budget)?),
+            }
+        }
+        Ok(results)
+    }
+
+    /// Reports every name of `program` that is bound neither in it nor by
+    /// previous programs, without evaluating anything.
+    pub fn check(&self, program: &Program) -> Result<(), Vec<InterpreterError>> {
+        let errors = check::unbound_names(program, |name| self.env.get(name).is_some());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
+
+    /// Parses, checks then runs `program`.
+    pub fn execute(&mut self, program: &str) -> Result<Vec<EvalResult>, ExecutionError> {
+        let program = parse_program(&lexer(program)?)?;
+        self.check(&program)?;
+        Ok(self.run(&program)?)
+    }
 }
 
-pub fn execute(program: &str) -> Result<EvalResult, Box<dyn Error>> {
-    Ok(parse(&lexer(program)?)?.eval()?)
+/// Runs `program` from a clean state.
+pub fn execute(program: &str) -> Result<Vec<EvalResult>, ExecutionError> {
+    Interpreter::new().execute(program)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::ast::*;
     use crate::interpreter::*;
+    use crate::parser::parse;
+
+    const LIMITS: Limits = Limits {
+        max_steps: Some(2000),
+        timeout: None,
+    };
+
+    /// Checks the result of both evaluators.
+    fn assert_eval(ast: &Expr, expected: Result<EvalResult, InterpreterError>) {
+        assert_eq!(ast.eval(), expected);
+        assert_eq!(ast.eval_machine(), expected);
+    }
 
     #[test]
     fn test_simple_addition_lamda() {
@@ -167,7 +599,7 @@ mod tests {
             ),
             Expr::numeric_literal(1.),
         );
-        assert_eq!(ast.eval(), Ok(EvalResult::Value(2.)));
+        assert_eval(&ast, Ok(EvalResult::Value(2.)));
     }
 
     #[test]
@@ -177,7 +609,7 @@ mod tests {
             Expr::numeric_literal(2.),
             Expr::numeric_literal(1.),
         );
-        assert_eq!(ast.eval(), Ok(EvalResult::Boolean(true)))
+        assert_eval(&ast, Ok(EvalResult::Boolean(true)))
     }
 
     #[test]
@@ -187,7 +619,55 @@ mod tests {
             Expr::numeric_literal(1.),
             Expr::numeric_literal(2.),
         );
-        assert_eq!(ast.eval(), Ok(EvalResult::Value(1.)))
+        assert_eval(&ast, Ok(EvalResult::Value(1.)))
+    }
+
+    #[test]
+    fn test_unary() {
+        let ast = Expr::arithmetic(
+            ArithmeticOp::Sub,
+            Expr::unary(UnaryOp::Neg, Expr::numeric_literal(2.)),
+            Expr::unary(UnaryOp::Neg, Expr::numeric_literal(3.)),
+        );
+        assert_eval(&ast, Ok(EvalResult::Value(1.)));
+        let ast = Expr::unary(
+            UnaryOp::Not,
+            Expr::comparison(
+                ComparisonOp::Gt,
+                Expr::numeric_literal(1.),
+                Expr::numeric_literal(2.),
+            ),
+        );
+        assert_eval(&ast, Ok(EvalResult::Boolean(true)));
+        assert!(matches!(
+            Expr::unary(UnaryOp::Not, Expr::numeric_literal(1.)).eval(),
+            Err(InterpreterError::TypeError { .. })
+        ));
+        assert!(matches!(
+            Expr::unary(UnaryOp::Neg, Expr::boolean_literal(true)).eval_machine(),
+            Err(InterpreterError::TypeError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_logical() {
+        let parse = |program: &str| parse(&lexer(program).unwrap()).unwrap();
+        assert_eval(
+            &parse("1 < 2 && 2 < 3 || false"),
+            Ok(EvalResult::Boolean(true)),
+        );
+        assert_eval(&parse("!true || 1 > 2"), Ok(EvalResult::Boolean(false)));
+        // The right operand is not evaluated when the left one decides the result
+        assert_eval(&parse("false && y"), Ok(EvalResult::Boolean(false)));
+        assert_eval(&parse("true || (1 2)"), Ok(EvalResult::Boolean(true)));
+        assert!(matches!(
+            parse("true && 1").eval(),
+            Err(InterpreterError::TypeError { .. })
+        ));
+        assert!(matches!(
+            parse("1 || true").eval_machine(),
+            Err(InterpreterError::TypeError { .. })
+        ));
     }
 
     #[test]
@@ -201,7 +681,7 @@ mod tests {
             Expr::numeric_literal(1.),
             Expr::numeric_literal(2.),
         );
-        assert_eq!(ast.eval(), Ok(EvalResult::Value(2.)));
+        assert_eval(&ast, Ok(EvalResult::Value(2.)));
     }
 
     #[test]
@@ -225,7 +705,7 @@ mod tests {
                 Expr::numeric_literal(2.),
             ),
         );
-        assert_eq!(ast.eval(), Ok(EvalResult::Value(3.)));
+        assert_eval(&ast, Ok(EvalResult::Value(3.)));
     }
 
     #[test]
@@ -276,6 +756,257 @@ mod tests {
 
         let fib_5 = Expr::app(fib, Expr::numeric_literal(5.));
 
-        assert_eq!(fib_5.eval(), Ok(EvalResult::Value(8.)));
+        assert_eval(&fib_5, Ok(EvalResult::Value(8.)));
+    }
+
+    #[test]
+    fn test_error_position() {
+        let expr = parse(&lexer("(\\x.x+y) 1").unwrap()).unwrap();
+        match expr.eval() {
+            Err(InterpreterError::NameError { name, span }) => {
+                assert_eq!(name, "y");
+                assert_eq!((span.start.offset, span.end.offset), (6, 7));
+            }
+            other => panic!("expected a name error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check_before_evaluation() {
+        let mut interpreter = Interpreter::new();
+        interpreter.execute(r"let one = 1").unwrap();
+        match interpreter.execute(r"one = 1 ? 2 : tow + tree") {
+            Err(ExecutionError::Check(errors)) => {
+                let names: Vec<_> = errors
+                    .iter()
+                    .map(|error| match error {
+                        InterpreterError::NameError { name, .. } => name.as_str(),
+                        other => panic!("expected a name error, got {:?}", other),
+                    })
+                    .collect();
+                assert_eq!(names, ["tow", "tree"]);
+            }
+            other => panic!("expected name errors, got {:?}", other),
+        }
+        // `x` would be a type error if it was evaluated
+        assert!(matches!(
+            interpreter.execute(r"let x = 1 + true; x y"),
+            Err(ExecutionError::Check(errors)) if errors.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_type_error_diagnostic() {
+        let program = "1+1 2";
+        let Err(error) = execute(program) else {
+            panic!("expected a type error");
+        };
+        assert_eq!(
+            error.diagnostics()[0].render("test.lc", program),
+            "error: type error
+ --> test.lc:1:1
+  |
+1 | 1+1 2
+  | ^^^ expected a Closure, found a Value
+  |
+  = help: only functions can be applied to an argument
+"
+        );
+    }
+
+    #[test]
+    fn test_multiline_program() {
+        let program = "let double = \\x.\n\tx * 2;\r\ndouble 4;\ndouble true\n";
+        let Err(error) = Interpreter::new().execute(program) else {
+            panic!("expected a type error");
+        };
+        assert_eq!(
+            error.diagnostics()[0].render("test.lc", program),
+            "error: type error
+ --> test.lc:2:2
+  |
+2 | \tx * 2;
+  | \t^ expected a Value, found a Boolean
+"
+        );
+        assert_eq!(
+            execute("let double = \\x.\n\tx * 2;\ndouble 4"),
+            Ok(vec![EvalResult::Value(8.)])
+        );
+    }
+
+    #[test]
+    fn test_definitions() {
+        let program = concat!(
+            r"let Y = \f.(\x.f (\v.x x v)) (\x.f (\v.x x v)); ",
+            r"let fib = Y (\f.\x.x<2 ? 1 : (f x-1) + (f x-2)); ",
+            "fib 5; fib 10"
+        );
+        assert_eq!(
+            execute(program),
+            Ok(vec![EvalResult::Value(8.), EvalResult::Value(89.)])
+        );
+    }
+
+    #[test]
+    fn test_definitions_are_kept() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.execute(r"let add = \x.\y.x+y"), Ok(vec![]));
+        assert_eq!(
+            interpreter.execute("add 1 2"),
+            Ok(vec![EvalResult::Value(3.)])
+        );
+    }
+
+    #[test]
+    fn test_failing_program_defines_nothing() {
+        let mut interpreter = Interpreter::new().with_limits(LIMITS);
+        assert_eq!(interpreter.execute(r"let add = \x.\y.x+y"), Ok(vec![]));
+        assert!(interpreter.execute("let x = 1; true + 1").is_err());
+        assert!(interpreter.execute("x").is_err());
+        let diverging = r"let c = let rec f = \n.f n in f 1";
+        assert!(interpreter.execute(diverging).is_err());
+        assert!(interpreter.execute("c").is_err());
+        // The definitions of previous programs are still there
+        assert_eq!(
+            interpreter.execute("add 2 2"),
+            Ok(vec![EvalResult::Value(4.)])
+        );
+    }
+
+    #[test]
+    fn test_let_rec() {
+        let program = r"let rec fib = \x.x<2 ? 1 : (fib x-1) + (fib x-2) in fib 10";
+        assert_eq!(execute(program), Ok(vec![EvalResult::Value(89.)]));
+
+        let program = r"let rec fact = \x.x=0 ? 1 : x * (fact x-1); fact 5";
+        assert_eq!(execute(program), Ok(vec![EvalResult::Value(120.)]));
+    }
+
+    /// Checks the result of both evaluators with `strategy`.
+    fn assert_strategy(
+        program: &str,
+        strategy: Strategy,
+        expected: Result<EvalResult, InterpreterError>,
+    ) {
+        let expr = parse(&lexer(program).unwrap()).unwrap();
+        assert_eq!(expr.eval_with(strategy, LIMITS), expected, "{}", program);
+        assert_eq!(
+            expr.eval_machine_with(strategy, LIMITS),
+            expected,
+            "{}",
+            program
+        );
+    }
+
+    #[test]
+    fn test_lazy_strategies() {
+        let lazy_fib = concat!(
+            r"(\f.(\x.f (x x)) (\x.f (x x)))",
+            r"(\f.\x.x<2 ? 1 : (f x-1) + (f x-2)) 5"
+        );
+        let unused_loop = r"(\x.1) ((\x.x x) (\x.x x))";
+        for strategy in [Strategy::CallByName, Strategy::CallByNeed] {
+            assert_strategy(lazy_fib, strategy, Ok(EvalResult::Value(8.)));
+            assert_strategy(unused_loop, strategy, Ok(EvalResult::Value(1.)));
+        }
+        let expr = parse(&lexer(unused_loop).unwrap()).unwrap();
+        assert!(matches!(
+            expr.eval_machine_with_limits(LIMITS),
+            Err(InterpreterError::ResourceExhausted { .. })
+        ));
+    }
+
+    #[test]
+    fn test_call_by_need_sharing() {
+        // The argument takes about 800 steps, it fits in the budget only if
+        // it is evaluated once.
+        let program = r"(\x.x+x+x) (let rec f = \n.n=0 ? 0 : f (n-1) in f 80)";
+        assert_strategy(program, Strategy::CallByValue, Ok(EvalResult::Value(0.)));
+        assert_strategy(program, Strategy::CallByNeed, Ok(EvalResult::Value(0.)));
+        let expr = parse(&lexer(program).unwrap()).unwrap();
+        for result in [
+            expr.eval_with(Strategy::CallByName, LIMITS),
+            expr.eval_machine_with(Strategy::CallByName, LIMITS),
+        ] {
+            assert!(matches!(
+                result,
+                Err(InterpreterError::ResourceExhausted { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_lazy_definitions() {
+        let program = "let x = 1 + true; 2";
+        let mut interpreter = Interpreter::new().with_strategy(Strategy::CallByNeed);
+        assert_eq!(
+            interpreter.execute(program),
+            Ok(vec![EvalResult::Value(2.)])
+        );
+        assert!(execute(program).is_err());
+    }
+
+    #[test]
+    fn test_show_closures() {
+        let show_with = |strategy, program: &str, style| {
+            let results = Interpreter::new()
+                .with_strategy(strategy)
+                .execute(program)
+                .unwrap();
+            results[0].show(style, Printer::new())
+        };
+        let show = |program: &str, style| show_with(Strategy::CallByValue, program, style);
+        use ClosureStyle::*;
+        assert_eq!(show(r"\x.x+1", Substituted), r"\x.x + 1");
+        assert_eq!(show(r"(\x.\y.x+y) 2", Substituted), r"\y.2 + y");
+        assert_eq!(
+            show(r"(\x.\y.x+y) 2", Environment),
+            r"<closure \y.x + y where x = 2>"
+        );
+        assert_eq!(show(r"(\f.\x.f x) \y.y", Substituted), r"\x.(\y.y) x");
+        assert_eq!(
+            show(r"(\f.\x.f x) \y.y", Environment),
+            r"<closure \x.f x where f = <closure \y.y>>"
+        );
+        let lazy = |program: &str, style| show_with(Strategy::CallByNeed, program, style);
+        assert_eq!(lazy(r"(\x.\y.x) (1+2)", Substituted), r"\y.1 + 2");
+        assert_eq!(
+            lazy(r"(\x.\y.x) (1+2)", Environment),
+            r"<closure \y.x where x = 1 + 2>"
+        );
+        assert_eq!(
+            show(r"let rec f = \x.f x in f", Substituted),
+            r"let rec f = \x.f x in f"
+        );
+        assert_eq!(execute(r"(\x.\y.x) 2").unwrap()[0].to_string(), r"\y.2");
+    }
+
+    #[test]
+    fn test_show_non_finite_numbers() {
+        for (program, shown) in [("1/0", "1 / 0"), ("-1/0", "-(1 / 0)"), ("0/0", "0 / 0")] {
+            let value = execute(program).unwrap().remove(0);
+            assert_eq!(value.to_string(), shown);
+            // What is shown evaluates back to the same number
+            let EvalResult::Value(number) = value else {
+                panic!("{} is not a number", program);
+            };
+            let EvalResult::Value(again) = execute(shown).unwrap().remove(0) else {
+                panic!("{} is not a number", shown);
+            };
+            assert!(
+                number == again || number.is_nan() && again.is_nan(),
+                "{}",
+                program
+            );
+        }
+    }
+
+    /// Substituting the captured variables used to cost twice as much for each binder.
+    #[test]
+    fn test_show_deeply_curried_closure() {
+        let binders: String = (1..=60).map(|i| format!(r"\a{}.", i)).collect();
+        let results = execute(&format!(r"(\x.{}x) 1", binders)).unwrap();
+        assert_eq!(results[0].to_string(), format!("{}1", binders));
     }
 }
@@ -0,0 +1,823 @@
+use std::{collections::HashSet, fmt::Display, rc::Rc};
+
+use crate::{
+    ast::{Expr, ExprKind, Item, Program, UnaryOp},
+    interpreter::{
+        error::{ExecutionError, InterpreterError},
+        limits::{Budget, Limits},
+    },
+    lexer::lexer,
+    parser::parse_program,
+    printer::Printer,
+};
+
+/// Which redex is reduced first.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Order {
+    /// The leftmost outermost redex, which finds the normal form whenever there is one.
+    #[default]
+    Normal,
+    /// The leftmost innermost redex, so that arguments are in normal form when they
+    /// are substituted. Recursive functions never reach a normal form in this order.
+    Applicative,
+}
+
+/// A fresh name based on `name` that is not in `used`. The number is written
+/// before the primes, which must end names: `x'` becomes `x1'`.
+fn fresh_name(name: &str, used: &HashSet<Rc<str>>) -> Rc<str> {
+    let base = name.trim_end_matches('\'');
+    let primes = &name[base.len()..];
+    (1..)
+        .map(|i| Rc::from(format!("{}{}{}", base, i, primes)))
+        .find(|candidate| !used.contains(candidate))
+        .unwrap()
+}
+
+/// Replaces the free occurrences of `name` in `expr` by `value`, renaming the
+/// binders of `expr` that would capture a free variable of `value`.
+///
+/// `free` are the free variables of `value`. Returns `None` when `name` is not
+/// free in `expr`, so that unchanged sub-expressions are shared instead of copied.
+fn substitute(
+    expr: &Rc<Expr>,
+    name: &str,
+    value: &Rc<Expr>,
+    free: &HashSet<Rc<str>>,
+) -> Option<Rc<Expr>> {
+    let subst = |expr: &Rc<Expr>| substitute(expr, name, value, free);
+    let kind = match &expr.kind {
+        ExprKind::Var { name: var } if **var == *name => return Some(Rc::clone(value)),
+        ExprKind::Var { .. }
+        | ExprKind::NumericLiteral { .. }
+        | ExprKind::BooleanLiteral { .. }
+        | ExprKind::Error => return None,
+        ExprKind::Abs { var, .. } | ExprKind::LetRec { name: var, .. } if **var == *name => {
+            return None
+        }
+        ExprKind::Abs { var, ty, body } => {
+            let substituted = subst(body)?;
+            // The renamed body is substituted again, which only happens when `var`
+            // would capture a variable of `value`
+            let (var, body) = if free.contains(var) {
+                let (var, [body]) = rename(var, [body], free);
+                (var, subst(&body).unwrap_or(body))
+            } else {
+                (Rc::clone(var), substituted)
+            };
+            ExprKind::Abs {
+                var,
+                ty: ty.clone(),
+                body,
+            }
+        }
+        ExprKind::LetRec {
+            name: var,
+            ty,
+            value: bound,
+            body,
+        } => {
+            let (substituted_bound, substituted_body) = both(bound, body, subst)?;
+            let (var, [bound, body]) = if free.contains(var) {
+                let (var, children) = rename(var, [bound, body], free);
+                (var, children.map(|child| subst(&child).unwrap_or(child)))
+            } else {
+                (Rc::clone(var), [substituted_bound, substituted_body])
+            };
+            ExprKind::LetRec {
+                name: var,
+                ty: ty.clone(),
+                value: bound,
+                body,
+            }
+        }
+        ExprKind::App {
+            function,
+            parameter,
+        } => {
+            let (function, parameter) = both(function, parameter, subst)?;
+            ExprKind::App {
+                function,
+                parameter,
+            }
+        }
+        ExprKind::Arithmetic {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            let (lhs, rhs) = both(lhs, rhs, subst)?;
+            ExprKind::Arithmetic {
+                operation: operation.clone(),
+                lhs,
+                rhs,
+            }
+        }
+        ExprKind::Comparison {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            let (lhs, rhs) = both(lhs, rhs, subst)?;
+            ExprKind::Comparison {
+                operation: operation.clone(),
+                lhs,
+                rhs,
+            }
+        }
+        ExprKind::Logical {
+            operation,
+            lhs,
+            rhs,
+        } => {
+            let (lhs, rhs) = both(lhs, rhs, subst)?;
+            ExprKind::Logical {
+                operation: operation.clone(),
+                lhs,
+                rhs,
+            }
+        }
+        ExprKind::Unary { operation, operand } => ExprKind::Unary {
+            operation: operation.clone(),
+            operand: subst(operand)?,
+        },
+        ExprKind::Conditional {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            let (condition, true_branch, false_branch) =
+                match (subst(condition), subst(true_branch), subst(false_branch)) {
+                    (None, None, None) => return None,
+                    (c, t, f) => (
+                        c.unwrap_or_else(|| Rc::clone(condition)),
+                        t.unwrap_or_else(|| Rc::clone(true_branch)),
+                        f.unwrap_or_else(|| Rc::clone(false_branch)),
+                    ),
+                };
+            ExprKind::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            }
+        }
+    };
+    Some(Rc::new(Expr::new(kind).with_span(expr.span)))
+}
+
+/// Applies `f` to both expressions, `None` if neither is changed.
+fn both(
+    lhs: &Rc<Expr>,
+    rhs: &Rc<Expr>,
+    f: impl Fn(&Rc<Expr>) -> Option<Rc<Expr>>,
+) -> Option<(Rc<Expr>, Rc<Expr>)> {
+    match (f(lhs), f(rhs)) {
+        (None, None) => None,
+        (new_lhs, new_rhs) => Some((
+            new_lhs.unwrap_or_else(|| Rc::clone(lhs)),
+            new_rhs.unwrap_or_else(|| Rc::clone(rhs)),
+        )),
+    }
+}
+
+/// Renames `var` in the `scope` it binds, as it is one of the `free` variables
+/// of the value being substituted, which would otherwise be captured.
+fn rename<const N: usize>(
+    var: &Rc<str>,
+    scope: [&Rc<Expr>; N],
+    free: &HashSet<Rc<str>>,
+) -> (Rc<str>, [Rc<Expr>; N]) {
+    let mut used = free.clone();
+    for expr in scope {
+        used.extend(expr.free_vars());
+    }
+    let fresh = fresh_name(var, &used);
+    let replacement = Rc::new(Expr::new(ExprKind::Var {
+        name: Rc::clone(&fresh),
+    }));
+    let no_free = HashSet::from([Rc::clone(&fresh)]);
+    let scope = scope.map(|expr| {
+        substitute(expr, var, &replacement, &no_free).unwrap_or_else(|| Rc::clone(expr))
+    });
+    (fresh, scope)
+}
+
+/// `body` where `var` is replaced by `argument`.
+pub(super) fn beta(var: &str, body: &Rc<Expr>, argument: &Rc<Expr>) -> Rc<Expr> {
+    substitute(body, var, argument, &argument.free_vars()).unwrap_or_else(|| Rc::clone(body))
+}
+
+/// Whether `expr` is `let rec name = value in name`, the recursive function
+/// itself, which is only unfolded when it is applied.
+fn is_fixpoint(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::LetRec { name, body, .. } => {
+            matches!(&body.kind, ExprKind::Var { name: var } if var == name)
+        }
+        _ => false,
+    }
+}
+
+/// Replaces `name` in `value` by the recursive function itself.
+fn unfold(name: &str, value: &Rc<Expr>, fixpoint: &Rc<Expr>) -> Rc<Expr> {
+    beta(name, value, fixpoint)
+}
+
+/// The rewriting rule applied by a reduction step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    /// `(\x.body) argument` becomes `body` where `x` is replaced by `argument`.
+    Beta,
+    /// A `let rec` is replaced by its body, where the name is replaced by the function.
+    Unfold,
+    /// An arithmetic operator applied to literals is replaced by its result.
+    Arithmetic,
+    /// A comparison between literals is replaced by its result.
+    Comparison,
+    /// `-` or `!` applied to a literal is replaced by its result.
+    Negation,
+    /// `&&` or `||` whose left operand is a literal is replaced by its result,
+    /// or by its right operand when it is a literal too.
+    Logical,
+    /// A conditional whose condition is a literal is replaced by a branch.
+    Conditional,
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rule = match self {
+            Rule::Beta => "beta reduction",
+            Rule::Unfold => "let rec unfolding",
+            Rule::Arithmetic => "arithmetic",
+            Rule::Comparison => "comparison",
+            Rule::Negation => "negation",
+            Rule::Logical => "logical operator",
+            Rule::Conditional => "conditional",
+        };
+        write!(f, "{}", rule)
+    }
+}
+
+/// The rewriting of `redex`, a sub-expression of the reduced expression, with `rule`.
+struct Reduction {
+    /// The reduced expression after the rewriting.
+    result: Rc<Expr>,
+    redex: Rc<Expr>,
+    rule: Rule,
+}
+
+impl Reduction {
+    /// The same reduction seen from the parent of the reduced expression.
+    fn within(self, rebuild: impl FnOnce(Rc<Expr>) -> Rc<Expr>) -> Self {
+        Self {
+            result: rebuild(self.result),
+            ..self
+        }
+    }
+}
+
+/// The result of a reduction step, `None` when there was nothing to reduce.
+type Step = Result<Option<Reduction>, InterpreterError>;
+
+/// Reduces the first redex of `expr` in `order`, `None` if `expr` is in normal form.
+///
+/// Besides beta reduction, operators are applied to literals, conditionals with
+/// a literal condition are replaced by a branch, and `let rec` are unfolded.
+/// Operators applied to anything else are left as they are.
+fn step(expr: &Rc<Expr>, order: Order) -> Step {
+    let step = |expr: &Rc<Expr>| step(expr, order);
+    let new = |kind: ExprKind| Rc::new(Expr::new(kind).with_span(expr.span));
+    let reduce = |rule: Rule, result: Rc<Expr>| {
+        Some(Reduction {
+            result,
+            redex: Rc::clone(expr),
+            rule,
+        })
+    };
+    let reduced = match &expr.kind {
+        ExprKind::Var { .. }
+        | ExprKind::NumericLiteral { .. }
+        | ExprKind::BooleanLiteral { .. } => None,
+        ExprKind::Error => return Err(InterpreterError::InvalidExpression { span: expr.span }),
+        ExprKind::Abs { var, ty, body } => step(body)?.map(|reduction| {
+            reduction.within(|body| {
+                new(ExprKind::Abs {
+                    var: Rc::clone(var),
+                    ty: ty.clone(),
+                    body,
+                })
+            })
+        }),
+        ExprKind::App {
+            function,
+            parameter,
+        } => {
+            let redex = || match &function.kind {
+                ExprKind::Abs { var, body, .. } => reduce(Rule::Beta, beta(var, body, parameter)),
+                ExprKind::LetRec { name, value, .. } if is_fixpoint(function) => reduce(
+                    Rule::Unfold,
+                    new(ExprKind::App {
+                        function: unfold(name, value, function),
+                        parameter: Rc::clone(parameter),
+                    }),
+                ),
+                _ => None,
+            };
+            if order == Order::Normal {
+                if let Some(reduction) = redex() {
+                    return Ok(Some(reduction));
+                }
+            }
+            if let Some(reduction) = step(function)? {
+                Some(reduction.within(|function| {
+                    new(ExprKind::App {
+                        function,
+                        parameter: Rc::clone(parameter),
+                    })
+                }))
+            } else if let Some(reduction) = step(parameter)? {
+                Some(reduction.within(|parameter| {
+                    new(ExprKind::App {
+                        function: Rc::clone(function),
+                        parameter,
+                    })
+                }))
+            } else {
+                redex()
+            }
+        }
+        ExprKind::Arithmetic {
+            operation,
+            lhs,
+            rhs,
+        } => match (&lhs.kind, &rhs.kind) {
+            (ExprKind::NumericLiteral { value: x }, ExprKind::NumericLiteral { value: y }) => {
+                reduce(
+                    Rule::Arithmetic,
+                    new(ExprKind::NumericLiteral {
+                        value: operation.eval(*x, *y),
+                    }),
+                )
+            }
+            _ => step_operands(lhs, rhs, step, |lhs, rhs| {
+                new(ExprKind::Arithmetic {
+                    operation: operation.clone(),
+                    lhs,
+                    rhs,
+                })
+            })?,
+        },
+        ExprKind::Comparison {
+            operation,
+            lhs,
+            rhs,
+        } => match (&lhs.kind, &rhs.kind) {
+            (ExprKind::NumericLiteral { value: x }, ExprKind::NumericLiteral { value: y }) => {
+                reduce(
+                    Rule::Comparison,
+                    new(ExprKind::BooleanLiteral {
+                        value: operation.eval(*x, *y),
+                    }),
+                )
+            }
+            _ => step_operands(lhs, rhs, step, |lhs, rhs| {
+                new(ExprKind::Comparison {
+                    operation: operation.clone(),
+                    lhs,
+                    rhs,
+                })
+            })?,
+        },
+        ExprKind::Logical {
+            operation,
+            lhs,
+            rhs,
+        } => match (&lhs.kind, &rhs.kind) {
+            (ExprKind::BooleanLiteral { value }, _)
+                if operation.short_circuit(*value).is_some() =>
+            {
+                reduce(Rule::Logical, Rc::clone(lhs))
+            }
+            (ExprKind::BooleanLiteral { .. }, ExprKind::BooleanLiteral { .. }) => {
+                reduce(Rule::Logical, Rc::clone(rhs))
+            }
+            _ => step_operands(lhs, rhs, step, |lhs, rhs| {
+                new(ExprKind::Logical {
+                    operation: operation.clone(),
+                    lhs,
+                    rhs,
+                })
+            })?,
+        },
+        ExprKind::Unary { operation, operand } => match (operation, &operand.kind) {
+            (UnaryOp::Neg, ExprKind::NumericLiteral { value }) => reduce(
+                Rule::Negation,
+                new(ExprKind::NumericLiteral { value: -value }),
+            ),
+            (UnaryOp::Not, ExprKind::BooleanLiteral { value }) => reduce(
+                Rule::Negation,
+                new(ExprKind::BooleanLiteral { value: !value }),
+            ),
+            _ => step(operand)?.map(|reduction| {
+                reduction.within(|operand| {
+                    new(ExprKind::Unary {
+                        operation: operation.clone(),
+                        operand,
+                    })
+                })
+            }),
+        },
+        ExprKind::Conditional {
+            condition,
+            true_branch,
+            false_branch,
+        } => match condition.kind {
+            ExprKind::BooleanLiteral { value: true } => {
+                reduce(Rule::Conditional, Rc::clone(true_branch))
+            }
+            ExprKind::BooleanLiteral { value: false } => {
+                reduce(Rule::Conditional, Rc::clone(false_branch))
+            }
+            _ => {
+                // The branches are only reduced if the condition is stuck
+                let new = |condition, true_branch, false_branch| {
+                    new(ExprKind::Conditional {
+                        condition,
+                        true_branch,
+                        false_branch,
+                    })
+                };
+                if let Some(reduction) = step(condition)? {
+                    Some(reduction.within(|condition| {
+                        new(condition, Rc::clone(true_branch), Rc::clone(false_branch))
+                    }))
+                } else if let Some(reduction) = step(true_branch)? {
+                    Some(reduction.within(|true_branch| {
+                        new(Rc::clone(condition), true_branch, Rc::clone(false_branch))
+                    }))
+                } else {
+                    step(false_branch)?.map(|reduction| {
+                        reduction.within(|false_branch| {
+                            new(Rc::clone(condition), Rc::clone(true_branch), false_branch)
+                        })
+                    })
+                }
+            }
+        },
+        ExprKind::LetRec { .. } if is_fixpoint(expr) => None,
+        ExprKind::LetRec {
+            name,
+            ty,
+            value,
+            body,
+        } => {
+            let fixpoint = new(ExprKind::LetRec {
+                name: Rc::clone(name),
+                ty: ty.clone(),
+                value: Rc::clone(value),
+                body: Rc::new(Expr::new(ExprKind::Var {
+                    name: Rc::clone(name),
+                })),
+            });
+            reduce(Rule::Unfold, beta(name, body, &fixpoint))
+        }
+    };
+    Ok(reduced)
+}
+
+/// Reduces the left operand, or the right one if the left one is in normal form,
+/// and `rebuild`s the operator with them.
+fn step_operands(
+    lhs: &Rc<Expr>,
+    rhs: &Rc<Expr>,
+    step: impl Fn(&Rc<Expr>) -> Step,
+    rebuild: impl FnOnce(Rc<Expr>, Rc<Expr>) -> Rc<Expr>,
+) -> Step {
+    Ok(if let Some(reduction) = step(lhs)? {
+        Some(reduction.within(|lhs| rebuild(lhs, Rc::clone(rhs))))
+    } else {
+        step(rhs)?.map(|reduction| reduction.within(|rhs| rebuild(Rc::clone(lhs), rhs)))
+    })
+}
+
+/// A reduction step, displayed as the expression with the redex underlined.
+#[derive(Debug, Clone, Copy)]
+pub struct TraceStep<'a> {
+    /// The expression before the step.
+    pub expr: &'a Expr,
+    /// The sub-expression of `expr` that is rewritten.
+    pub redex: &'a Expr,
+    pub rule: Rule,
+}
+
+impl TraceStep<'_> {
+    /// Writes the step with `printer`.
+    pub fn show(&self, printer: Printer) -> String {
+        let (text, range) = printer.print_locating(self.expr, self.redex);
+        let range = range.unwrap_or(0..text.len());
+        let padding = text[..range.start].chars().count();
+        let width = text[range].chars().count().max(1);
+        format!(
+            "{}\n{}{} {}",
+            text,
+            " ".repeat(padding),
+            "^".repeat(width),
+            self.rule
+        )
+    }
+}
+
+impl Display for TraceStep<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.show(Printer::new()))
+    }
+}
+
+impl Expr {
+    /// Performs a single reduction step in `order`, `None` if the expression is
+    /// in normal form.
+    pub fn reduce_step(&self, order: Order) -> Result<Option<Expr>, InterpreterError> {
+        Ok(step(&Rc::new(self.clone()), order)?
+            .map(|reduction| Rc::unwrap_or_clone(reduction.result)))
+    }
+
+    /// Rewrites the expression to its normal form by reducing redexes in `order`,
+    /// including under abstractions. A step of `limits` is consumed per reduction.
+    pub fn reduce(&self, order: Order, limits: Limits) -> Result<Expr, InterpreterError> {
+        self.reduce_traced(order, limits, |_| {})
+    }
+
+    /// Reduces the expression like `reduce`, calling `on_step` before each step.
+    pub fn reduce_traced(
+        &self,
+        order: Order,
+        limits: Limits,
+        mut on_step: impl FnMut(TraceStep),
+    ) -> Result<Expr, InterpreterError> {
+        let mut budget = Budget::new(limits);
+        let mut expr = Rc::new(self.clone());
+        while let Some(reduction) = step(&expr, order)? {
+            budget.step(reduction.redex.span)?;
+            on_step(TraceStep {
+                expr: &expr,
+                redex: &reduction.redex,
+                rule: reduction.rule,
+            });
+            expr = reduction.result;
+        }
+        Ok(Rc::unwrap_or_clone(expr))
+    }
+}
+
+/// Reduces programs one after the other, keeping the definitions made by the
+/// previous ones, like `Interpreter` but returning normal forms instead of values.
+#[derive(Debug, Default)]
+pub struct Reducer {
+    /// Definitions made so far, most recent last. Their values don't refer to
+    /// previous definitions, which are substituted when they are made.
+    definitions: Vec<(Rc<str>, Rc<Expr>)>,
+    order: Order,
+    limits: Limits,
+}
+
+impl Reducer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_order(self, order: Order) -> Self {
+        Self { order, ..self }
+    }
+
+    /// Bounds the number of reductions made by each call to `run`.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// `expr` where the names defined so far are replaced by their value.
+    fn substitute_definitions(&self, expr: &Expr) -> Expr {
+        let mut expr = Rc::new(expr.clone());
+        for (name, value) in self.definitions.iter().rev() {
+            expr = beta(name, &expr, value);
+        }
+        Rc::unwrap_or_clone(expr)
+    }
+
+    /// Makes `value` available as `name` in the following programs.
+    pub fn define(&mut self, name: Rc<str>, value: &Expr) {
+        let value = Rc::new(self.substitute_definitions(value));
+        self.definitions.push((name, value));
+    }
+
+    /// Reduces the expressions of `program` in order and returns their normal form.
+    ///
+    /// Definitions are not reduced, they are substituted in the items that follow.
+    /// Like with `Interpreter::run`, they are only kept if no item fails.
+    pub fn run(&mut self, program: &Program) -> Result<Vec<Expr>, InterpreterError> {
+        self.run_traced(program, |_| {})
+    }
+
+    /// Reduces `program` like `run`, calling `on_step` before each reduction step.
+    pub fn run_traced(
+        &mut self,
+        program: &Program,
+        mut on_step: impl FnMut(TraceStep),
+    ) -> Result<Vec<Expr>, InterpreterError> {
+        let defined = self.definitions.len();
+        let mut results = vec![];
+        for item in &program.items {
+            match item {
+                Item::Definition { name, value } => self.define(Rc::clone(name), value),
+                Item::Expression(expr) => {
+                    let result = self.substitute_definitions(expr).reduce_traced(
+                        self.order,
+                        self.limits,
+                        &mut on_step,
+                    );
+                    match result {
+                        Ok(result) => results.push(result),
+                        Err(error) => {
+                            self.definitions.truncate(defined);
+                            return Err(error);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    pub fn execute(&mut self, program: &str) -> Result<Vec<Expr>, ExecutionError> {
+        self.execute_traced(program, |_| {})
+    }
+
+    pub fn execute_traced(
+        &mut self,
+        program: &str,
+        on_step: impl FnMut(TraceStep),
+    ) -> Result<Vec<Expr>, ExecutionError> {
+        let program = parse_program(&lexer(program)?)?;
+        Ok(self.run_traced(&program, on_step)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::Expr,
+        interpreter::{error::InterpreterError, limits::Limits, reduce::*},
+        lexer::lexer,
+        parser::parse,
+    };
+
+    fn expr(program: &str) -> Expr {
+        parse(&lexer(program).unwrap()).unwrap()
+    }
+
+    fn normal_form(program: &str, order: Order) -> String {
+        let limits = Limits {
+            max_steps: Some(10_000),
+            timeout: None,
+        };
+        expr(program).reduce(order, limits).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_reduce_under_abstraction() {
+        assert_eq!(normal_form(r"(\x.\y.x) 1", Order::Normal), r"\y.1");
+        assert_eq!(normal_form(r"\x.(\y.y+1) x", Order::Normal), r"\x.x + 1");
+        assert_eq!(
+            normal_form(r"\f.\x.f (f x)", Order::Applicative),
+            r"\f.\x.f (f x)"
+        );
+    }
+
+    #[test]
+    fn test_capture_avoiding_substitution() {
+        assert_eq!(normal_form(r"(\x.\y.x y) y", Order::Normal), r"\y1.y y1");
+        assert_eq!(
+            normal_form(r"(\x.\y.\y1.x y y1) y", Order::Normal),
+            r"\y1.\y11.y y1 y11"
+        );
+        assert_eq!(normal_form(r"(\x.\x.x) 1", Order::Normal), r"\x.x");
+        assert_eq!(
+            normal_form(r"(\x.\y'.x y') y'", Order::Normal),
+            r"\y1'.y' y1'"
+        );
+    }
+
+    /// Each binder used to substitute its body twice, doubling the cost.
+    #[test]
+    fn test_deeply_curried() {
+        let binders: String = (1..=60).map(|i| format!(r"\a{}.", i)).collect();
+        assert_eq!(
+            normal_form(&format!(r"(\x.{}x) 1", binders), Order::Normal),
+            format!("{}1", binders)
+        );
+        assert_eq!(
+            normal_form(&format!(r"(\x.\y.{}x y) y", binders), Order::Normal),
+            format!(r"\y1.{}y y1", binders)
+        );
+    }
+
+    #[test]
+    fn test_orders() {
+        let omega = r"((\x.x x) (\x.x x))";
+        assert_eq!(normal_form(&format!(r"(\x.1) {omega}"), Order::Normal), "1");
+        let result = expr(&format!(r"(\x.1) {omega}")).reduce(
+            Order::Applicative,
+            Limits {
+                max_steps: Some(100),
+                timeout: None,
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(InterpreterError::ResourceExhausted { .. })
+        ));
+        assert_eq!(
+            normal_form(r"(\x.x+x) ((\y.y*2) 3)", Order::Applicative),
+            "12"
+        );
+    }
+
+    #[test]
+    fn test_recursion() {
+        let fact = r"let rec fact = \n.n=0 ? 1 : n * (fact n-1) in fact 5";
+        assert_eq!(normal_form(fact, Order::Normal), "120");
+        let y = r"(\f.(\x.f (x x)) (\x.f (x x)))";
+        let fib = format!(r"{y} (\f.\x.x<2 ? 1 : (f x-1) + (f x-2)) 6");
+        assert_eq!(normal_form(&fib, Order::Normal), "13");
+    }
+
+    #[test]
+    fn test_steps() {
+        let mut expr = expr(r"(\x.x+1) 2");
+        let mut steps = vec![];
+        while let Some(reduced) = expr.reduce_step(Order::Normal).unwrap() {
+            steps.push(reduced.to_string());
+            expr = reduced;
+        }
+        assert_eq!(steps, ["2 + 1", "3"]);
+        assert_eq!(normal_form(r"(\x.-x) 2", Order::Normal), "-2");
+        assert_eq!(normal_form(r"\x.!(1 > 2)", Order::Normal), r"\x.true");
+        assert_eq!(normal_form(r"\x.-x", Order::Normal), r"\x.-x");
+        assert_eq!(normal_form(r"\x.false && x", Order::Normal), r"\x.false");
+        assert_eq!(normal_form(r"(\x.x > 1 || x) 2", Order::Normal), "true");
+        assert_eq!(
+            normal_form(r"\x.x && 1 < 2", Order::Normal),
+            r"\x.x && true"
+        );
+    }
+
+    #[test]
+    fn test_reducer_definitions() {
+        let mut reducer = Reducer::new();
+        assert_eq!(
+            reducer
+                .execute(r"let K = \x.\y.x; let I = \x.x; K I; K I 1 2")
+                .unwrap()
+                .iter()
+                .map(Expr::to_string)
+                .collect::<Vec<_>>(),
+            [r"\y.\x.x", "2"]
+        );
+    }
+
+    #[test]
+    fn test_reducer_definitions_are_kept() {
+        let limits = Limits {
+            max_steps: Some(1000),
+            timeout: None,
+        };
+        let mut reducer = Reducer::new().with_limits(limits);
+        assert_eq!(reducer.execute(r"let I = \x.x"), Ok(vec![]));
+        let omega = r"(\x.x x) (\x.x x)";
+        assert!(reducer.execute(&format!("let K = I; {}", omega)).is_err());
+        // `K` was rolled back with the failing program, but not `I`
+        assert_eq!(
+            reducer.execute("I K").map(|results| results[0].to_string()),
+            Ok("K".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut trace = vec![];
+        let result = expr(r"(\x.\y.y x) 1 ((\x.\y.x+y) 2)")
+            .reduce_traced(Order::Applicative, Limits::default(), |step| {
+                trace.push(step.to_string())
+            })
+            .unwrap();
+        assert_eq!(result.to_string(), "3");
+        assert_eq!(
+            trace,
+            [
+                "(\\x.\\y.y x) 1 ((\\x.\\y.x + y) 2)\n^^^^^^^^^^^^^ beta reduction",
+                "(\\y.y 1) ((\\x.\\y.x + y) 2)\n          ^^^^^^^^^^^^^^^ beta reduction",
+                "(\\y.y 1) \\y.2 + y\n^^^^^^^^^^^^^^^^^ beta reduction",
+                "(\\y.2 + y) 1\n^^^^^^^^^^^^ beta reduction",
+                "2 + 1\n^^^^^ arithmetic",
+            ]
+        );
+    }
+}
@@ -1,8 +1,19 @@
 use std::{error::Error, fmt::Display};
 
+use crate::{
+    diagnostic::{Diagnostic, ToDiagnostic},
+    span::Span,
+};
+
 #[derive(Debug, PartialEq)]
 pub enum LexerError {
-    IllegalCharacterError(char),
+    IllegalCharacterError(char, Span),
+    /// The text at the span starts with a digit but is not a number.
+    MalformedNumberError(String, Span),
+    /// The text at the span starts like a name but is not one.
+    MalformedNameError(String, Span),
+    /// A block comment opened at the span is never closed.
+    UnterminatedCommentError(Span),
     EmptyProgramError,
 }
 
@@ -11,10 +22,25 @@ impl Error for LexerError {}
 impl Display for LexerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::IllegalCharacterError(c) => write!(
+            Self::IllegalCharacterError(c, span) => write!(
+                f,
+                "Illegal Character Error: character '{}' at {} is an illegal character",
+                c, span
+            ),
+            Self::MalformedNumberError(text, span) => write!(
+                f,
+                "Malformed Number Error: '{}' at {} is not a valid number",
+                text, span
+            ),
+            Self::MalformedNameError(text, span) => write!(
+                f,
+                "Malformed Name Error: '{}' at {} is not a valid name",
+                text, span
+            ),
+            Self::UnterminatedCommentError(span) => write!(
                 f,
-                "Illegal Character Error: character '{}' is an illegal character",
-                c
+                "Unterminated Comment Error: the comment opened at {} is never closed",
+                span
             ),
             Self::EmptyProgramError => {
                 write!(f, "Empty Program Error : the provided program is empty")
@@ -22,3 +48,28 @@ impl Display for LexerError {
         }
     }
 }
+
+impl ToDiagnostic for LexerError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            Self::IllegalCharacterError(c, span) => {
+                Diagnostic::new(format!("illegal character `{}`", c))
+                    .with_label(*span, "this character isn't part of the language")
+            }
+            Self::MalformedNumberError(text, span) => {
+                Diagnostic::new(format!("malformed number `{}`", text))
+                    .with_label(*span, "this is not a valid number")
+                    .with_help("numbers are written like `42`, `2.5`, `1e-3`, `0xff` or `1_000`")
+            }
+            Self::MalformedNameError(text, span) => {
+                Diagnostic::new(format!("malformed name `{}`", text))
+                    .with_label(*span, "this is not a valid name")
+                    .with_help("primes can only end names, like in `x'`")
+            }
+            Self::UnterminatedCommentError(span) => Diagnostic::new("unterminated block comment")
+                .with_label(*span, "this comment is never closed")
+                .with_help("block comments end with `-}`, once for each `{-`"),
+            Self::EmptyProgramError => Diagnostic::new("the provided program is empty"),
+        }
+    }
+}
@@ -1,6 +1,11 @@
 pub mod error;
 
-use crate::lexer::error::LexerError;
+use std::{fmt::Display, iter::Peekable, str::CharIndices};
+
+use crate::{
+    lexer::error::LexerError,
+    span::{Position, Span, Spanned},
+};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Paren {
@@ -21,17 +26,54 @@ pub enum Op {
     Eq,
     Neq,
     Not,
+    And,
+    Or,
+}
+
+/// Words that can't be used as names.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Keyword {
+    Let,
+    Rec,
+    In,
+    True,
+    False,
+}
+
+impl Keyword {
+    /// The keyword spelled `word`, if any.
+    fn from_word(word: &str) -> Option<Self> {
+        match word {
+            "let" => Some(Keyword::Let),
+            "rec" => Some(Keyword::Rec),
+            "in" => Some(Keyword::In),
+            "true" => Some(Keyword::True),
+            "false" => Some(Keyword::False),
+            _ => None,
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Token {
     Lambda,
+    /// A name, made of letters, digits and `_`, not starting with a digit, and
+    /// possibly ending with primes, like `fib_2` or `x'`.
     Identifier(String),
+    Keyword(Keyword),
+    /// A numeric literal, like `42`, `2.5`, `1e-3`, `0xff` or `1_000`.
+    Number(f64),
     Dot,
     Parentheses(Paren),
     Colon,
     QuestionMark,
+    Semicolon,
+    /// `->`, in the types of functions.
+    Arrow,
     Operator(Op),
+    /// A `-- line comment` or a `{- block comment -}`, as written. Only kept
+    /// by `lexer_with_comments`.
+    Comment(String),
 }
 
 impl Token {
@@ -40,54 +82,335 @@ impl Token {
     }
 }
 
-pub fn lexer(prog: &str) -> Result<Vec<Token>, LexerError> {
-    if prog.len() == 0 {
+impl Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Op::Plus => "+",
+            Op::Minus => "-",
+            Op::Asterisk => "*",
+            Op::Slash => "/",
+            Op::Sup => ">",
+            Op::SupEq => ">=",
+            Op::Inf => "<",
+            Op::InfEq => "<=",
+            Op::Eq => "=",
+            Op::Neq => "!=",
+            Op::Not => "!",
+            Op::And => "&&",
+            Op::Or => "||",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for Keyword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = match self {
+            Keyword::Let => "let",
+            Keyword::Rec => "rec",
+            Keyword::In => "in",
+            Keyword::True => "true",
+            Keyword::False => "false",
+        };
+        write!(f, "{}", keyword)
+    }
+}
+
+/// Displays the token as it is written in the source code.
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Lambda => write!(f, "\\"),
+            Token::Identifier(id) => write!(f, "{}", id),
+            Token::Keyword(keyword) => write!(f, "{}", keyword),
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Dot => write!(f, "."),
+            Token::Parentheses(Paren::Open) => write!(f, "("),
+            Token::Parentheses(Paren::Close) => write!(f, ")"),
+            Token::Colon => write!(f, ":"),
+            Token::QuestionMark => write!(f, "?"),
+            Token::Semicolon => write!(f, ";"),
+            Token::Arrow => write!(f, "->"),
+            Token::Operator(op) => write!(f, "{}", op),
+            Token::Comment(comment) => write!(f, "{}", comment),
+        }
+    }
+}
+
+/// Walks through the source code while keeping track of the current position.
+struct Cursor<'a> {
+    prog: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(prog: &'a str) -> Self {
+        Self {
+            prog,
+            chars: prog.char_indices().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn position(&mut self) -> Position {
+        Position {
+            offset: self.chars.peek().map_or(self.prog.len(), |(i, _)| *i),
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// The character `n` characters after the next one.
+    fn lookahead(&mut self, n: usize) -> Option<char> {
+        let offset = self.position().offset;
+        self.prog[offset..].chars().nth(n)
+    }
+
+    /// Consumes characters as long as they match `predicate` and returns them.
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.position().offset;
+        while self.peek().is_some_and(&predicate) {
+            self.bump();
+        }
+        &self.prog[start..self.position().offset]
+    }
+}
+
+/// Reads the number starting at the cursor, which is on a digit.
+///
+/// A decimal point must be followed by a digit, so that `\x.1.5` is the function
+/// returning 1.5 and `1.` is `1` followed by a dot. Underscores can separate digits.
+fn number(cursor: &mut Cursor) -> Result<Spanned<Token>, LexerError> {
+    let start = cursor.position();
+    let is_digit = |c: char| c.is_ascii_digit() || c == '_';
+    let hexadecimal = cursor.peek() == Some('0')
+        && matches!(cursor.lookahead(1), Some('x' | 'X'))
+        && cursor.lookahead(2).is_some_and(|c| c.is_ascii_hexdigit());
+    if hexadecimal {
+        cursor.bump();
+        cursor.bump();
+        cursor.eat_while(|c| c.is_ascii_hexdigit() || c == '_');
+    } else {
+        cursor.eat_while(is_digit);
+        if cursor.peek() == Some('.') && cursor.lookahead(1).is_some_and(|c| c.is_ascii_digit()) {
+            cursor.bump();
+            cursor.eat_while(is_digit);
+        }
+        if matches!(cursor.peek(), Some('e' | 'E')) {
+            let sign = usize::from(matches!(cursor.lookahead(1), Some('+' | '-')));
+            if cursor
+                .lookahead(1 + sign)
+                .is_some_and(|c| c.is_ascii_digit())
+            {
+                for _ in 0..=sign {
+                    cursor.bump();
+                }
+                cursor.eat_while(is_digit);
+            }
+        }
+    }
+    // Letters right after a number, like in `12ab` or `0x`, make it malformed
+    let malformed = cursor
+        .peek()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_');
+    cursor.eat_while(|c| c.is_alphanumeric() || c == '_');
+    let span = Span::new(start, cursor.position());
+    let text = &cursor.prog[start.offset..span.end.offset];
+    let digits = text.replace('_', "");
+    let value = if malformed {
+        None
+    } else if hexadecimal {
+        u64::from_str_radix(&digits[2..], 16).ok().map(|n| n as f64)
+    } else {
+        digits.parse::<f64>().ok()
+    };
+    match value {
+        Some(value) => Ok(Spanned::new(Token::Number(value), span)),
+        None => Err(LexerError::MalformedNumberError(text.to_owned(), span)),
+    }
+}
+
+/// Whether `c` can be part of a name. `λ` is a letter but always starts an
+/// abstraction, like `\`.
+fn is_name_char(c: char) -> bool {
+    (c.is_alphanumeric() || c == '_') && c != 'λ'
+}
+
+/// Reads the name or keyword starting at the cursor, which is on a letter or `_`.
+fn word(cursor: &mut Cursor) -> Result<Spanned<Token>, LexerError> {
+    let start = cursor.position();
+    cursor.eat_while(is_name_char);
+    cursor.eat_while(|c| c == '\'');
+    // Primes can only end a name, like in `x'`
+    let malformed = cursor.peek().is_some_and(is_name_char);
+    cursor.eat_while(|c| is_name_char(c) || c == '\'');
+    let span = Span::new(start, cursor.position());
+    let word = &cursor.prog[start.offset..span.end.offset];
+    if malformed {
+        return Err(LexerError::MalformedNameError(word.to_owned(), span));
+    }
+    let token = match Keyword::from_word(word) {
+        Some(keyword) => Token::Keyword(keyword),
+        None => Token::identifier(word),
+    };
+    Ok(Spanned::new(token, span))
+}
+
+/// Splits `prog` into tokens, skipping whitespace and comments.
+pub fn lexer(prog: &str) -> Result<Vec<Spanned<Token>>, LexerError> {
+    let mut tokens = lexer_with_comments(prog)?;
+    tokens.retain(|token| !matches!(token.value, Token::Comment(_)));
+    // A program made only of comments has nothing to run
+    if tokens.is_empty() {
+        return Err(LexerError::EmptyProgramError);
+    }
+    Ok(tokens)
+}
+
+/// Splits `prog` into tokens like `lexer`, keeping comments as `Token::Comment`.
+///
+/// `--` starts a comment that ends with the line, `{-` a comment that ends with
+/// the matching `-}`, so that block comments can be nested.
+pub fn lexer_with_comments(prog: &str) -> Result<Vec<Spanned<Token>>, LexerError> {
+    if prog.is_empty() {
         return Err(LexerError::EmptyProgramError);
     }
 
-    let mut last = 0;
-    let mut res = vec![];
-    for (index, matched) in prog.match_indices(|c: char| !c.is_alphanumeric()) {
-        let sep = matched.chars().next().unwrap(); // should never panic
-        let between = &prog[last..index];
-        if (!between.chars().any(|c| !c.is_alphanumeric())) && between.len() > 0 {
-            res.push(Token::identifier(between));
+    let mut res: Vec<Spanned<Token>> = vec![];
+    let mut cursor = Cursor::new(prog);
+    while let Some(c) = cursor.peek() {
+        let start = cursor.position();
+        if c.is_ascii_digit() {
+            res.push(number(&mut cursor)?);
+            continue;
         }
-        last = index + 1;
-        let token = match sep {
-            '\\' => Token::Lambda,
+        if (c.is_alphabetic() || c == '_') && c != 'λ' {
+            res.push(word(&mut cursor)?);
+            continue;
+        }
+        cursor.bump();
+        let end = cursor.position();
+        let token = match c {
+            '\\' | 'λ' => Token::Lambda,
             '.' => Token::Dot,
             '(' => Token::Parentheses(Paren::Open),
             ')' => Token::Parentheses(Paren::Close),
             '?' => Token::QuestionMark,
             ':' => Token::Colon,
+            ';' => Token::Semicolon,
+            '-' if cursor.peek() == Some('-') => {
+                cursor.eat_while(|c| c != '\n');
+                let end = cursor.position();
+                res.push(Spanned::new(
+                    Token::Comment(prog[start.offset..end.offset].to_owned()),
+                    Span::new(start, end),
+                ));
+                continue;
+            }
+            '{' if cursor.peek() == Some('-') => {
+                cursor.bump();
+                let opening = Span::new(start, cursor.position());
+                let mut depth = 1;
+                while depth > 0 {
+                    match (cursor.bump(), cursor.peek()) {
+                        (Some('{'), Some('-')) => depth += 1,
+                        (Some('-'), Some('}')) => depth -= 1,
+                        (Some(_), _) => continue,
+                        (None, _) => return Err(LexerError::UnterminatedCommentError(opening)),
+                    }
+                    cursor.bump();
+                }
+                let end = cursor.position();
+                res.push(Spanned::new(
+                    Token::Comment(prog[start.offset..end.offset].to_owned()),
+                    Span::new(start, end),
+                ));
+                continue;
+            }
             '+' => Token::Operator(Op::Plus),
             '-' => Token::Operator(Op::Minus),
             '*' => Token::Operator(Op::Asterisk),
             '/' => Token::Operator(Op::Slash),
-            '>' => Token::Operator(Op::Sup),
-            '<' => Token::Operator(Op::Inf),
-            '=' => match res.pop() {
-                Some(el) => match el {
-                    Token::Operator(Op::Sup) => Token::Operator(Op::SupEq),
-                    Token::Operator(Op::Inf) => Token::Operator(Op::InfEq),
-                    Token::Operator(Op::Not) => Token::Operator(Op::Neq),
-                    other => {
-                        res.push(other);
-                        Token::Operator(Op::Eq)
-                    }
-                },
-                None => Token::Operator(Op::Eq),
+            '>' => match res.last() {
+                // `->` is only recognized when both characters are adjacent
+                Some(previous)
+                    if previous.span.end == start
+                        && previous.value == Token::Operator(Op::Minus) =>
+                {
+                    let previous = res.pop().unwrap(); // cannot fail, `res.last()` is Some
+                    res.push(Spanned::new(
+                        Token::Arrow,
+                        Span::new(previous.span.start, end),
+                    ));
+                    continue;
+                }
+                _ => Token::Operator(Op::Sup),
             },
+            '<' => Token::Operator(Op::Inf),
+            '=' => {
+                // `>=`, `<=` and `!=` are only recognized when both characters are adjacent
+                let combined = match res.last() {
+                    Some(previous) if previous.span.end == start => match previous.value {
+                        Token::Operator(Op::Sup) => Some(Op::SupEq),
+                        Token::Operator(Op::Inf) => Some(Op::InfEq),
+                        Token::Operator(Op::Not) => Some(Op::Neq),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some(op) = combined {
+                    let previous = res.pop().unwrap(); // cannot fail, `res.last()` is Some
+                    res.push(Spanned::new(
+                        Token::Operator(op),
+                        Span::new(previous.span.start, end),
+                    ));
+                    continue;
+                }
+                Token::Operator(Op::Eq)
+            }
             '!' => Token::Operator(Op::Not),
-            ' ' => continue,
-            other => return Err(LexerError::IllegalCharacterError(other)),
+            '≤' => Token::Operator(Op::InfEq),
+            '≥' => Token::Operator(Op::SupEq),
+            '≠' => Token::Operator(Op::Neq),
+            '→' => Token::Arrow,
+            // A single `&` or `|` is not an operator
+            '&' | '|' if cursor.peek() == Some(c) => {
+                cursor.bump();
+                let op = if c == '&' { Op::And } else { Op::Or };
+                res.push(Spanned::new(
+                    Token::Operator(op),
+                    Span::new(start, cursor.position()),
+                ));
+                continue;
+            }
+            // Spaces, tabs and line breaks only separate tokens
+            c if c.is_whitespace() => continue,
+            other => {
+                return Err(LexerError::IllegalCharacterError(
+                    other,
+                    Span::new(start, end),
+                ))
+            }
         };
-        res.push(token);
-    }
-    let between = &prog[last..prog.len()];
-    if (!between.chars().any(|c| !c.is_alphanumeric())) && between.len() > 0 {
-        res.push(Token::Identifier(between.to_owned()));
+        res.push(Spanned::new(token, Span::new(start, end)));
     }
     Ok(res)
 }
@@ -96,10 +419,14 @@ pub fn lexer(prog: &str) -> Result<Vec<Token>, LexerError> {
 mod tests {
     use crate::lexer::*;
 
+    fn tokens(prog: &str) -> Result<Vec<Token>, LexerError> {
+        lexer(prog).map(|tokens| tokens.into_iter().map(|token| token.value).collect())
+    }
+
     #[test]
     fn test_simple() {
         assert_eq!(
-            lexer(r"\x.x"),
+            tokens(r"\x.x"),
             Ok(vec![
                 Token::Lambda,
                 Token::identifier("x"),
@@ -109,7 +436,7 @@ mod tests {
         );
 
         assert_eq!(
-            lexer(r"(\x.x+1) 1"),
+            tokens(r"(\x.x+1) 1"),
             Ok(vec![
                 Token::Parentheses(Paren::Open),
                 Token::Lambda,
@@ -117,31 +444,55 @@ mod tests {
                 Token::Dot,
                 Token::identifier("x"),
                 Token::Operator(Op::Plus),
-                Token::identifier("1"),
+                Token::Number(1.),
                 Token::Parentheses(Paren::Close),
-                Token::identifier("1"),
+                Token::Number(1.),
             ])
         );
     }
 
     #[test]
     fn test_two_chars_operators() {
-        assert_eq!(lexer(r">="), Ok(vec![Token::Operator(Op::SupEq)]));
+        assert_eq!(tokens(r">="), Ok(vec![Token::Operator(Op::SupEq)]));
         assert_eq!(
-            lexer(r"x!=y"),
+            tokens(r"x!=y"),
             Ok(vec![
                 Token::identifier("x"),
                 Token::Operator(Op::Neq),
                 Token::identifier("y")
             ])
         );
+        assert_eq!(
+            tokens(r"a&&b || c"),
+            Ok(vec![
+                Token::identifier("a"),
+                Token::Operator(Op::And),
+                Token::identifier("b"),
+                Token::Operator(Op::Or),
+                Token::identifier("c")
+            ])
+        );
+        assert!(matches!(
+            lexer("a & b"),
+            Err(LexerError::IllegalCharacterError('&', _))
+        ));
+        assert_eq!(
+            tokens(r"Num->Bool - >"),
+            Ok(vec![
+                Token::identifier("Num"),
+                Token::Arrow,
+                Token::identifier("Bool"),
+                Token::Operator(Op::Minus),
+                Token::Operator(Op::Sup)
+            ])
+        );
     }
 
     #[test]
     fn test_fib() {
         use Token::*;
         assert_eq!(
-            lexer(r"\f.\x. x>2 ? f(x-1) + f(x-2) : 1"),
+            tokens(r"\f.\x. x>2 ? f(x-1) + f(x-2) : 1"),
             Ok(vec![
                 Lambda,
                 Token::identifier("f"),
@@ -151,24 +502,250 @@ mod tests {
                 Dot,
                 Token::identifier("x"),
                 Operator(Op::Sup),
-                Token::identifier("2"),
+                Token::Number(2.),
                 QuestionMark,
                 Token::identifier("f"),
                 Parentheses(Paren::Open),
                 Token::identifier("x"),
                 Operator(Op::Minus),
-                Token::identifier("1"),
+                Token::Number(1.),
                 Parentheses(Paren::Close),
                 Operator(Op::Plus),
                 Token::identifier("f"),
                 Parentheses(Paren::Open),
                 Token::identifier("x"),
                 Operator(Op::Minus),
-                Token::identifier("2"),
+                Token::Number(2.),
                 Parentheses(Paren::Close),
                 Colon,
-                Token::identifier("1")
+                Token::Number(1.)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let tokens = lexer(r"(\x.x) >= 12").unwrap();
+        let spans: Vec<(usize, usize)> = tokens
+            .iter()
+            .map(|token| (token.span.start.offset, token.span.end.offset))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0, 1),
+                (1, 2),
+                (2, 3),
+                (3, 4),
+                (4, 5),
+                (5, 6),
+                (7, 9),
+                (10, 12)
+            ]
+        );
+        assert_eq!(tokens[7].span.start.column, 11);
+    }
+
+    #[test]
+    fn test_whitespace() {
+        assert_eq!(
+            tokens("\tx\r\n+\u{a0}1\n"),
+            Ok(vec![
+                Token::identifier("x"),
+                Token::Operator(Op::Plus),
+                Token::Number(1.)
+            ])
+        );
+        let tokens = lexer("let f = \\x.x;\n\n  f 1").unwrap();
+        let start = tokens[8].span.start;
+        assert_eq!((start.offset, start.line, start.column), (17, 3, 3));
+        assert_eq!(tokens[9].span.end.column, 6);
+    }
+
+    #[test]
+    fn test_unicode_symbols() {
+        assert_eq!(tokens(r"λx.x ≤ 1"), tokens(r"\x.x <= 1"));
+        assert_eq!(tokens("a ≥ b ≠ c"), tokens("a >= b != c"));
+        assert_eq!(tokens("Num → Bool"), tokens("Num -> Bool"));
+        // `λ` is not part of the names around it
+        assert_eq!(
+            tokens("fλx.x"),
+            Ok(vec![
+                Token::identifier("f"),
+                Token::Lambda,
+                Token::identifier("x"),
+                Token::Dot,
+                Token::identifier("x")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        for (number, value) in [
+            ("42", 42.),
+            ("2.5", 2.5),
+            ("1e3", 1000.),
+            ("2.5E-1", 0.25),
+            ("1e+2", 100.),
+            ("0xff", 255.),
+            ("0X1_0", 16.),
+            ("1_000_000", 1_000_000.),
+        ] {
+            assert_eq!(tokens(number), Ok(vec![Token::Number(value)]), "{}", number);
+        }
+        assert_eq!(
+            tokens(r"\x.2.5"),
+            Ok(vec![
+                Token::Lambda,
+                Token::identifier("x"),
+                Token::Dot,
+                Token::Number(2.5)
+            ])
+        );
+        // A dot that is not followed by a digit is not a decimal point
+        assert_eq!(tokens("1."), Ok(vec![Token::Number(1.), Token::Dot]));
+        assert_eq!(
+            tokens("x1 1-2"),
+            Ok(vec![
+                Token::identifier("x1"),
+                Token::Number(1.),
+                Token::Operator(Op::Minus),
+                Token::Number(2.)
+            ])
+        );
+        for malformed in [
+            "12ab",
+            "0x",
+            "1e",
+            "0xfg",
+            "2.5e",
+            "0x1_0000_0000_0000_0000",
+        ] {
+            assert!(
+                matches!(
+                    lexer(malformed),
+                    Err(LexerError::MalformedNumberError(text, _)) if text == malformed
+                ),
+                "{}",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            tokens(r"\x'.fib_norec _x x1 x''"),
+            Ok(vec![
+                Token::Lambda,
+                Token::identifier("x'"),
+                Token::Dot,
+                Token::identifier("fib_norec"),
+                Token::identifier("_x"),
+                Token::identifier("x1"),
+                Token::identifier("x''")
+            ])
+        );
+        assert_eq!(
+            tokens("let rec in true false letter"),
+            Ok(vec![
+                Token::Keyword(Keyword::Let),
+                Token::Keyword(Keyword::Rec),
+                Token::Keyword(Keyword::In),
+                Token::Keyword(Keyword::True),
+                Token::Keyword(Keyword::False),
+                Token::identifier("letter")
+            ])
+        );
+        for malformed in ["x'y", "x'1", "f''_"] {
+            assert!(
+                matches!(
+                    lexer(malformed),
+                    Err(LexerError::MalformedNameError(text, _)) if text == malformed
+                ),
+                "{}",
+                malformed
+            );
+        }
+        assert!(matches!(
+            lexer("'x"),
+            Err(LexerError::IllegalCharacterError('\'', _))
+        ));
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            tokens("x -- the rest of the line\n- {- a {- nested -} comment -} y --"),
+            Ok(vec![
+                Token::identifier("x"),
+                Token::Operator(Op::Minus),
+                Token::identifier("y")
+            ])
+        );
+        // Only adjacent dashes start a comment
+        assert_eq!(
+            tokens("- -x"),
+            Ok(vec![
+                Token::Operator(Op::Minus),
+                Token::Operator(Op::Minus),
+                Token::identifier("x")
             ])
         );
+        let comments: Vec<(Token, usize, usize)> = lexer_with_comments("{-a-}1 -- b\n")
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.value, token.span.start.offset, token.span.end.offset))
+            .collect();
+        assert_eq!(
+            comments,
+            [
+                (Token::Comment("{-a-}".to_owned()), 0, 5),
+                (Token::Number(1.), 5, 6),
+                (Token::Comment("-- b".to_owned()), 7, 11)
+            ]
+        );
+        let Err(LexerError::UnterminatedCommentError(span)) = lexer("1 {- {- -} 2") else {
+            panic!("expected an unterminated comment");
+        };
+        assert_eq!((span.start.offset, span.end.offset), (2, 4));
+    }
+
+    #[test]
+    fn test_comment_only_program() {
+        for program in ["--3", "{- x -}", " -- a\n {- b -} "] {
+            assert_eq!(
+                lexer(program),
+                Err(LexerError::EmptyProgramError),
+                "{}",
+                program
+            );
+        }
+        // Comments are still tokens when they are kept
+        let comments = lexer_with_comments("--3").unwrap();
+        assert_eq!(comments[0].value, Token::Comment("--3".to_owned()));
+    }
+
+    #[test]
+    fn test_illegal_character_position() {
+        assert_eq!(
+            lexer("1 + $"),
+            Err(LexerError::IllegalCharacterError(
+                '$',
+                Span::new(
+                    Position {
+                        offset: 4,
+                        line: 1,
+                        column: 5
+                    },
+                    Position {
+                        offset: 5,
+                        line: 1,
+                        column: 6
+                    }
+                )
+            ))
+        );
     }
 }
@@ -0,0 +1,9 @@
+pub mod ast;
+pub mod debruijn;
+pub mod diagnostic;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+pub mod printer;
+pub mod span;
+pub mod types;
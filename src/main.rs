@@ -1,33 +1,157 @@
+mod cli;
+
 use std::{
-    env,
-    error::Error,
-    fmt::Display,
-    fs,
+    env, fs,
     io::{stdin, stdout, Write},
     process::exit,
+    rc::Rc,
+};
+
+use lambda_calculus::{
+    ast::{Item, Program},
+    interpreter::{
+        check::Checker, error::ExecutionError, reduce::Reducer, ClosureStyle, Interpreter,
+    },
+    lexer::lexer,
+    parser::{parse, parse_program},
+    printer::Printer,
+    types::TypeChecker,
 };
 
-use crate::interpreter::execute;
+use crate::cli::{command, parse_args, Options};
 
-mod ast;
-mod interpreter;
-mod lexer;
-mod parser;
+/// Prints every diagnostic of `error` on the standard error.
+fn report(error: &ExecutionError, file_name: &str, source: &str) {
+    let rendered: Vec<String> = error
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| diagnostic.render(file_name, source))
+        .collect();
+    eprint!("{}", rendered.join("\n"));
+}
 
-#[derive(Debug)]
-struct CliError {}
+/// Runs programs as configured by the command line, keeping definitions between them.
+struct Session {
+    interpreter: Interpreter,
+    /// Also knows the definitions evaluated by `interpreter`, so that they can be traced.
+    reducer: Reducer,
+    /// Programs are reduced to their normal form instead of being evaluated.
+    reduce: bool,
+    /// The reduction steps are printed.
+    trace: bool,
+    /// Knows the definitions of the programs that were only checked.
+    checker: Checker,
+    /// Programs are checked for unbound names but not evaluated.
+    check: bool,
+    /// Also knows the definitions evaluated by `interpreter`, or reduced by `reducer`,
+    /// so that their type can be shown.
+    types: TypeChecker,
+    /// Programs that can't be typed are rejected.
+    typecheck: bool,
+    closures: ClosureStyle,
+    /// Writes the expressions and types in the results.
+    printer: Printer,
+}
 
-impl Error for CliError {}
+impl Session {
+    fn new(options: &Options) -> Self {
+        Self {
+            interpreter: Interpreter::new()
+                .with_strategy(options.strategy)
+                .with_limits(options.limits),
+            reducer: Reducer::new()
+                .with_order(options.reduce.unwrap_or_default())
+                .with_limits(options.limits),
+            reduce: options.reduce.is_some() || options.trace,
+            trace: options.trace,
+            checker: Checker::new(),
+            check: options.check,
+            types: TypeChecker::new().with_system(options.typecheck.unwrap_or_default()),
+            typecheck: options.typecheck.is_some(),
+            closures: options.closures,
+            printer: Printer::new().with_symbols(options.symbols),
+        }
+    }
 
-impl Display for CliError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Error: too manny parameters")
+    /// The results of `program`, as shown to the user.
+    fn execute(&mut self, program: &str) -> Result<Vec<String>, ExecutionError> {
+        let program = parse_program(&lexer(program)?)?;
+        if self.reduce {
+            let results = self.normal_forms(&program, self.trace)?;
+            for item in &program.items {
+                if let Item::Definition { name, value } = item {
+                    self.types.define(Rc::clone(name), value);
+                }
+            }
+            return Ok(results);
+        }
+        // The definitions are only kept once the whole program succeeded
+        let mut types = self.types.clone();
+        if self.check {
+            let mut checker = self.checker.clone();
+            checker.check(&program)?;
+            if self.typecheck {
+                types.check(&program)?;
+            }
+            self.checker = checker;
+            self.types = types;
+            return Ok(vec![]);
+        }
+        self.interpreter.check(&program)?;
+        if self.typecheck {
+            types.check(&program)?;
+        }
+        let results = self.interpreter.run(&program)?;
+        self.types = types;
+        for item in &program.items {
+            if let Item::Definition { name, value } = item {
+                self.reducer.define(Rc::clone(name), value);
+                if !self.typecheck {
+                    self.types.define(Rc::clone(name), value);
+                }
+            }
+        }
+        Ok(results
+            .iter()
+            .map(|result| result.show(self.closures, self.printer))
+            .collect())
+    }
+
+    /// The type of `expr`, as shown to the user.
+    fn type_of(&self, expr: &str) -> Result<String, ExecutionError> {
+        let expr = parse(&lexer(expr)?)?;
+        Ok(self.printer.print_type(&self.types.type_of(&expr)?))
+    }
+
+    /// The normal forms of `program`, printing each reduction step.
+    fn trace(&mut self, program: &str) -> Result<Vec<String>, ExecutionError> {
+        self.normal_forms(&parse_program(&lexer(program)?)?, true)
+    }
+
+    /// The normal forms of `program`, printing each reduction step if `trace` is set.
+    fn normal_forms(
+        &mut self,
+        program: &Program,
+        trace: bool,
+    ) -> Result<Vec<String>, ExecutionError> {
+        let results = self.reducer.run_traced(program, |step| {
+            if trace {
+                println!("{}", step.show(self.printer));
+            }
+        })?;
+        Ok(results
+            .iter()
+            .map(|result| self.printer.print(result))
+            .collect())
     }
 }
 
-fn interactive_mode() {
+fn interactive_mode(options: &Options) {
     println!("Welcome to the interactive mode of lambda calculus interpreter !");
     println!("Type \"quit\" or C-d to exit");
+    println!("Type \":trace\" before an expression to see its reduction steps");
+    println!("Type \":type\" before an expression to see its type");
+    let mut session = Session::new(options);
     let mut buf = String::new();
     loop {
         buf.clear();
@@ -36,34 +160,44 @@ fn interactive_mode() {
         if stdin().read_line(&mut buf).unwrap() == 0 || buf.trim() == "quit" {
             return;
         };
-        let buf = buf.trim();
-        match execute(buf) {
-            Ok(result) => println!("{}", result),
-            Err(error) => eprintln!("{}", error),
+        let buf = buf.trim_end();
+        let (buf, result) = if let Some(program) = command(buf, ":trace") {
+            (program, session.trace(program))
+        } else if let Some(expr) = command(buf, ":type") {
+            (expr, session.type_of(expr).map(|ty| vec![ty]))
+        } else {
+            (buf, session.execute(buf))
+        };
+        match result {
+            Ok(results) => results.iter().for_each(|result| println!("{}", result)),
+            Err(error) => report(&error, "<repl>", buf),
         };
     }
 }
 
-fn file_mode(file_path: &str) {
+fn file_mode(file_path: &str, options: &Options) {
     let contents = fs::read_to_string(file_path).expect("Should have been able to read the file");
-    match execute(contents.trim()) {
-        Ok(result) => println!("{}", result),
+    // Only the end is trimmed so that positions in errors match the file
+    match Session::new(options).execute(contents.trim_end()) {
+        Ok(results) => results.iter().for_each(|result| println!("{}", result)),
         Err(error) => {
-            eprintln!("{}", error);
+            report(&error, file_path, &contents);
             exit(1);
         }
     }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            exit(1);
+        }
+    };
 
-    if args.len() == 1 {
-        interactive_mode();
-    } else if args.len() == 2 {
-        file_mode(&args[1]);
-    } else {
-        eprintln!("CliError: Too many Arguments");
-        exit(1);
+    match &options.file {
+        None => interactive_mode(&options),
+        Some(file_path) => file_mode(file_path, &options),
     }
 }
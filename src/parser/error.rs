@@ -1,15 +1,39 @@
 use std::{error::Error, fmt::Display};
 
-use crate::lexer::Token;
+use crate::{
+    diagnostic::{Diagnostic, ToDiagnostic},
+    lexer::Token,
+    span::Span,
+};
 
 #[derive(Debug, PartialEq)]
-pub struct SyntaxError {
-    illegal_token: Token,
+pub enum SyntaxError {
+    IllegalToken {
+        token: Token,
+        span: Span,
+    },
+    /// An expression was expected after the token at `span` but there was none.
+    MissingExpression {
+        span: Span,
+    },
+    /// A specific token, described by `expected`, was needed at `span`.
+    ExpectedToken {
+        expected: String,
+        span: Span,
+    },
 }
 
 impl SyntaxError {
-    pub fn new(t: Token) -> Self {
-        Self { illegal_token: t }
+    pub fn new(t: Token, span: Span) -> Self {
+        Self::IllegalToken { token: t, span }
+    }
+
+    pub fn missing_expression(span: Span) -> Self {
+        Self::MissingExpression { span }
+    }
+
+    pub fn expected_token(expected: String, span: Span) -> Self {
+        Self::ExpectedToken { expected, span }
     }
 }
 
@@ -17,10 +41,35 @@ impl Error for SyntaxError {}
 
 impl Display for SyntaxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Syntax error: the token {:?} isn't authorized here.",
-            self.illegal_token
-        )
+        match self {
+            SyntaxError::IllegalToken { token, span } => write!(
+                f,
+                "Syntax error: the token {:?} at {} isn't authorized here.",
+                token, span
+            ),
+            SyntaxError::MissingExpression { span } => {
+                write!(f, "Syntax error: an expression is missing at {}.", span)
+            }
+            SyntaxError::ExpectedToken { expected, span } => {
+                write!(f, "Syntax error: expected {} at {}.", expected, span)
+            }
+        }
+    }
+}
+
+impl ToDiagnostic for SyntaxError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            SyntaxError::IllegalToken { token, span } => {
+                Diagnostic::new(format!("unexpected token `{}`", token))
+                    .with_label(*span, "this token isn't authorized here")
+            }
+            SyntaxError::MissingExpression { span } => Diagnostic::new("expected an expression")
+                .with_label(*span, "an expression is missing here"),
+            SyntaxError::ExpectedToken { expected, span } => {
+                Diagnostic::new(format!("expected {}", expected))
+                    .with_label(*span, format!("expected {} here", expected))
+            }
+        }
     }
 }
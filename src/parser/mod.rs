@@ -1,243 +1,949 @@
 pub mod error;
 
 use crate::{
-    ast::{ArithmeticOp, ComparisonOp, Expr},
-    lexer::{Op, Paren, Token},
+    ast::{ArithmeticOp, ComparisonOp, Expr, ExprKind, Item, LogicalOp, Program, UnaryOp},
+    lexer::{Keyword, Op, Paren, Token},
     parser::error::SyntaxError,
+    span::{Span, Spanned},
+    types::Type,
 };
 
-fn op_sup(op1: &Op, op2: &Op) -> bool {
-    (*op1 == Op::Asterisk || *op1 == Op::Slash) && (*op2 == Op::Plus || *op2 == Op::Minus)
+/// The operation of a binary expression, whose priority is given by the AST.
+enum BinaryOp {
+    Arithmetic(ArithmeticOp),
+    Comparison(ComparisonOp),
+    Logical(LogicalOp),
 }
 
-pub fn parse(tokens: &[Token]) -> Result<Expr, SyntaxError> {
-    // dbg!(tokens);
-    let tokens = remove_extra_parentheses(tokens);
-
-    // Trying to match Abs:
-    if let [Token::Lambda, Token::Identifier(id), Token::Dot, rest @ ..] = tokens {
-        return Ok(Expr::abs(id, parse(rest)?));
-    }
-
-    // Trying to match Conditional
-    let mut depth = 0;
-    for i in 0..tokens.len() {
-        if tokens[i] == Token::Parentheses(Paren::Close) {
-            depth += 1;
-        } else if tokens[i] == Token::Parentheses(Paren::Open) {
-            depth -= 1;
-        }
-        if tokens[i] == Token::QuestionMark && depth == 0 {
-            let mut depth = 0;
-            for j in (i + 1..tokens.len()).rev() {
-                if tokens[j] == Token::Parentheses(Paren::Close) {
-                    depth += 1;
-                } else if tokens[j] == Token::Parentheses(Paren::Open) {
-                    depth -= 1;
-                }
-                if tokens[j] == Token::Colon && depth == 0 {
-                    return Ok(Expr::conditional(
-                        parse(&tokens[0..i])?,
-                        parse(&tokens[i + 1..j])?,
-                        parse(&tokens[j + 1..tokens.len()])?,
-                    ));
-                }
-            }
+impl BinaryOp {
+    /// The binary operation written `op`, if `op` can be used between two operands.
+    fn from_token(op: &Op) -> Option<Self> {
+        let operation = match op {
+            Op::Plus => BinaryOp::Arithmetic(ArithmeticOp::Add),
+            Op::Minus => BinaryOp::Arithmetic(ArithmeticOp::Sub),
+            Op::Asterisk => BinaryOp::Arithmetic(ArithmeticOp::Mul),
+            Op::Slash => BinaryOp::Arithmetic(ArithmeticOp::Div),
+            Op::Sup => BinaryOp::Comparison(ComparisonOp::Gt),
+            Op::SupEq => BinaryOp::Comparison(ComparisonOp::Gte),
+            Op::Inf => BinaryOp::Comparison(ComparisonOp::Lt),
+            Op::InfEq => BinaryOp::Comparison(ComparisonOp::Lte),
+            Op::Eq => BinaryOp::Comparison(ComparisonOp::Eq),
+            Op::Neq => BinaryOp::Comparison(ComparisonOp::Neq),
+            Op::And => BinaryOp::Logical(LogicalOp::And),
+            Op::Or => BinaryOp::Logical(LogicalOp::Or),
+            Op::Not => return None,
+        };
+        Some(operation)
+    }
+
+    /// Operators of the same priority are left associative.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Arithmetic(operation) => operation.precedence(),
+            BinaryOp::Comparison(operation) => operation.precedence(),
+            BinaryOp::Logical(operation) => operation.precedence(),
         }
     }
 
-    // Trying to match App:
-    let mut depth = 0;
-    for i in (1..tokens.len()).rev() {
-        if tokens[i] == Token::Parentheses(Paren::Close) {
-            depth += 1;
-        } else if tokens[i] == Token::Parentheses(Paren::Open) {
-            depth -= 1;
+    fn apply(self, lhs: Expr, rhs: Expr) -> Expr {
+        match self {
+            BinaryOp::Arithmetic(operation) => Expr::arithmetic(operation, lhs, rhs),
+            BinaryOp::Comparison(operation) => Expr::comparison(operation, lhs, rhs),
+            BinaryOp::Logical(operation) => Expr::logical(operation, lhs, rhs),
         }
+    }
+}
 
-        if depth == 0 {
-            // We match try to find a non parenthesized Application
-            // Application is either Identifier then Anything Except operator
-            // or Closing paren then anything except operator
-            match tokens[i - 1] {
-                Token::Parentheses(Paren::Close) | Token::Identifier(_) => match tokens[i] {
-                    Token::Operator(_) | Token::QuestionMark | Token::Colon | Token::Dot => {
-                        continue
-                    }
-                    _ => {
-                        return Ok(Expr::app(
-                            parse(&tokens[0..i])?,
-                            parse(&tokens[i..tokens.len()])?,
-                        ))
-                    }
-                },
-                _ => continue,
-            }
+fn let_rec(name: &str, ty: Option<Type>, value: Expr, body: Expr) -> Expr {
+    match ty {
+        Some(ty) => Expr::annotated_let_rec(name, ty, value, body),
+        None => Expr::let_rec(name, value, body),
+    }
+}
+
+/// Recursive descent parser, the grammar in order of increasing priority is:
+///
+/// ```text
+/// program     := item (';' item)* ';'?
+/// item        := 'let' identifier '=' expr | 'let' 'rec' binder '=' expr | expr
+/// expr        := abstraction | let_rec | conditional
+/// let_rec     := 'let' 'rec' binder '=' abstraction 'in' expr
+/// abstraction := '\' binder '.' expr
+/// binder      := identifier (':' type)?
+/// conditional := application ('?' expr ':' expr)?
+/// application := binary binary*
+/// binary      := unary (operator unary)*    (precedence climbing)
+/// unary       := ('-' | '!') unary | atom
+/// atom        := literal | identifier | '(' expr ')' | abstraction
+/// type        := type_atom ('->' type)?
+/// type_atom   := 'Num' | 'Bool' | '(' type ')'
+/// ```
+///
+/// An abstraction extends as far to the right as possible, so it can appear
+/// as an operand without parentheses.
+///
+/// When a parenthesized expression cannot be parsed, the error is recorded and
+/// parsing resumes after the closing parenthesis, the expression being replaced
+/// by an `ExprKind::Error` node. Likewise, parsing resumes after the next `;`
+/// when a top-level item cannot be parsed.
+struct Parser<'a> {
+    tokens: &'a [Spanned<Token>],
+    position: usize,
+    errors: Vec<SyntaxError>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Spanned<Token>]) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            errors: vec![],
         }
     }
 
-    // It is not an abstraction, nor a conditional nor an application
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position).map(|token| &token.value)
+    }
 
-    // Trying to match Arithmetic and Comparison
+    fn next(&mut self) -> Option<&'a Spanned<Token>> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
 
-    let mut depth = 0;
-    let mut index = 0;
-    for (i, ref token) in tokens.iter().enumerate() {
-        if **token == Token::Parentheses(Paren::Open) {
-            depth += 1;
-        } else if **token == Token::Parentheses(Paren::Close) {
-            depth -= 1;
-        } else if let Token::Operator(op) = token {
-            if index != 0 {
-                if let Token::Operator(ref op2) = tokens[index] {
-                    if depth == 0 && !op_sup(op, op2) {
-                        index = i;
-                    }
-                }
-            } else if depth == 0 {
-                index = i;
-            }
+    /// Zero width span right after the last consumed token.
+    fn end_span(&self) -> Span {
+        match self
+            .position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+        {
+            Some(token) => Span::new(token.span.end, token.span.end),
+            None => Span::default(),
         }
     }
 
-    if index != 0 {
-        use Token::*;
-        return match tokens[index] {
-            Operator(Op::Plus) => Ok(Expr::arithmetic(
-                ArithmeticOp::Add,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
-            )),
-            Operator(Op::Minus) => Ok(Expr::arithmetic(
-                ArithmeticOp::Sub,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
-            )),
-            Operator(Op::Asterisk) => Ok(Expr::arithmetic(
-                ArithmeticOp::Mul,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
-            )),
-            Operator(Op::Slash) => Ok(Expr::arithmetic(
-                ArithmeticOp::Div,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
-            )),
-            Operator(Op::Sup) => Ok(Expr::comparison(
-                ComparisonOp::Gt,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
-            )),
-            Operator(Op::SupEq) => Ok(Expr::comparison(
-                ComparisonOp::Gte,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
+    /// The span from the token at index `start` to the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        match self.tokens.get(start) {
+            Some(first) => first
+                .span
+                .to(self.tokens[self.position.max(start + 1) - 1].span),
+            None => self.end_span(),
+        }
+    }
+
+    /// Reports the token at the current position as unexpected.
+    fn unexpected(&self) -> SyntaxError {
+        match self.tokens.get(self.position) {
+            Some(token) => SyntaxError::new(token.value.clone(), token.span),
+            None => SyntaxError::missing_expression(self.end_span()),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<&'a Spanned<Token>, SyntaxError> {
+        match self.tokens.get(self.position) {
+            Some(token) if token.value == expected => {
+                self.position += 1;
+                Ok(token)
+            }
+            Some(token) => Err(SyntaxError::expected_token(
+                format!("`{}`", expected),
+                token.span,
             )),
-            Operator(Op::Inf) => Ok(Expr::comparison(
-                ComparisonOp::Lt,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
+            None => Err(SyntaxError::expected_token(
+                format!("`{}`", expected),
+                self.end_span(),
             )),
-            Operator(Op::InfEq) => Ok(Expr::comparison(
-                ComparisonOp::Lte,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
+        }
+    }
+
+    /// Skips tokens up to and including the parenthesis closing the current one.
+    fn synchronize_parentheses(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token.value {
+                Token::Parentheses(Paren::Open) => depth += 1,
+                Token::Parentheses(Paren::Close) if depth == 0 => return,
+                Token::Parentheses(Paren::Close) => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips tokens up to and including the next `;`.
+    fn synchronize_item(&mut self) {
+        while let Some(token) = self.next() {
+            if token.value == Token::Semicolon {
+                return;
+            }
+        }
+    }
+
+    fn at_keyword(&self, keyword: Keyword) -> bool {
+        self.peek() == Some(&Token::Keyword(keyword))
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<(), SyntaxError> {
+        if self.at_keyword(keyword) {
+            self.position += 1;
+            return Ok(());
+        }
+        let span = match self.tokens.get(self.position) {
+            Some(token) => token.span,
+            None => self.end_span(),
+        };
+        Err(SyntaxError::expected_token(format!("`{}`", keyword), span))
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, SyntaxError> {
+        match self.tokens.get(self.position) {
+            Some(Spanned {
+                value: Token::Identifier(id),
+                ..
+            }) => {
+                self.position += 1;
+                Ok(id)
+            }
+            Some(token) => Err(SyntaxError::expected_token(
+                "an identifier".to_owned(),
+                token.span,
             )),
-            Operator(Op::Eq) => Ok(Expr::comparison(
-                ComparisonOp::Eq,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
+            None => Err(SyntaxError::expected_token(
+                "an identifier".to_owned(),
+                self.end_span(),
             )),
-            Operator(Op::Neq) => Ok(Expr::comparison(
-                ComparisonOp::Neq,
-                parse(&tokens[0..index])?,
-                parse(&tokens[index + 1..])?,
+        }
+    }
+
+    fn program(&mut self) -> Program {
+        let mut items = vec![];
+        while self.position < self.tokens.len() {
+            match self.item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize_item();
+                    continue;
+                }
+            }
+            match self.peek() {
+                None => break,
+                Some(Token::Semicolon) => {
+                    self.next();
+                }
+                Some(_) => {
+                    let error = self.unexpected();
+                    self.errors.push(error);
+                    self.synchronize_item();
+                }
+            }
+        }
+        Program { items }
+    }
+
+    /// A top-level `let rec f = value` defines `f` as `let rec f = value in f`.
+    fn item(&mut self) -> Result<Item, SyntaxError> {
+        if !self.at_keyword(Keyword::Let) {
+            return Ok(Item::Expression(self.expr()?));
+        }
+        let start = self.position;
+        self.next();
+        let recursive = self.at_keyword(Keyword::Rec);
+        if recursive {
+            self.next();
+        }
+        let name = self.identifier()?;
+        let ty = if recursive { self.annotation()? } else { None };
+        self.expect(Token::Operator(Op::Eq))?;
+        let value = if recursive {
+            self.recursive_value()?
+        } else {
+            self.expr()?
+        };
+        if !recursive {
+            return Ok(Item::Definition {
+                name: name.into(),
+                value,
+            });
+        }
+        if self.at_keyword(Keyword::In) {
+            self.next();
+            let body = self.expr()?;
+            let span = self.span_from(start);
+            return Ok(Item::Expression(
+                let_rec(name, ty, value, body).with_span(span),
+            ));
+        }
+        let span = self.span_from(start);
+        Ok(Item::Definition {
+            name: name.into(),
+            value: let_rec(name, ty, value, Expr::var(name).with_span(span)).with_span(span),
+        })
+    }
+
+    /// The type written after a bound name, if any.
+    fn annotation(&mut self) -> Result<Option<Type>, SyntaxError> {
+        if self.peek() != Some(&Token::Colon) {
+            return Ok(None);
+        }
+        self.next();
+        Ok(Some(self.ty()?))
+    }
+
+    fn ty(&mut self) -> Result<Type, SyntaxError> {
+        let parameter = self.type_atom()?;
+        if self.peek() != Some(&Token::Arrow) {
+            return Ok(parameter);
+        }
+        self.next();
+        Ok(Type::function(parameter, self.ty()?))
+    }
+
+    fn type_atom(&mut self) -> Result<Type, SyntaxError> {
+        let token = self.next();
+        match token.map(|token| &token.value) {
+            Some(Token::Identifier(id)) if id == "Num" => Ok(Type::Num),
+            Some(Token::Identifier(id)) if id == "Bool" => Ok(Type::Bool),
+            Some(Token::Parentheses(Paren::Open)) => {
+                let ty = self.ty()?;
+                self.expect(Token::Parentheses(Paren::Close))?;
+                Ok(ty)
+            }
+            _ => Err(SyntaxError::expected_token(
+                "a type".to_owned(),
+                token.map_or_else(|| self.end_span(), |token| token.span),
             )),
-            _ => panic!("Should never happen: {:?}", tokens[index]),
+        }
+    }
+
+    /// The value bound by `let rec`, which must be a function.
+    fn recursive_value(&mut self) -> Result<Expr, SyntaxError> {
+        let value = self.expr()?;
+        if !matches!(value.kind, ExprKind::Abs { .. }) {
+            return Err(SyntaxError::expected_token(
+                "an abstraction".to_owned(),
+                value.span,
+            ));
+        }
+        Ok(value)
+    }
+
+    fn let_rec(&mut self) -> Result<Expr, SyntaxError> {
+        let start = self.position;
+        self.expect_keyword(Keyword::Let)?;
+        self.expect_keyword(Keyword::Rec)?;
+        let name = self.identifier()?;
+        let ty = self.annotation()?;
+        self.expect(Token::Operator(Op::Eq))?;
+        let value = self.recursive_value()?;
+        self.expect_keyword(Keyword::In)?;
+        let body = self.expr()?;
+        Ok(let_rec(name, ty, value, body).with_span(self.span_from(start)))
+    }
+
+    fn expr(&mut self) -> Result<Expr, SyntaxError> {
+        if self.peek() == Some(&Token::Lambda) {
+            return self.abstraction();
+        }
+        if self.at_keyword(Keyword::Let) {
+            return self.let_rec();
+        }
+        let start = self.position;
+        let condition = self.application()?;
+        if self.peek() != Some(&Token::QuestionMark) {
+            return Ok(condition);
+        }
+        self.next();
+        let true_branch = self.expr()?;
+        self.expect(Token::Colon)?;
+        let false_branch = self.expr()?;
+        Ok(
+            Expr::conditional(condition, true_branch, false_branch)
+                .with_span(self.span_from(start)),
+        )
+    }
+
+    fn abstraction(&mut self) -> Result<Expr, SyntaxError> {
+        let start = self.position;
+        self.expect(Token::Lambda)?;
+        let var = self.identifier()?;
+        let ty = self.annotation()?;
+        self.expect(Token::Dot)?;
+        let body = self.expr()?;
+        let abs = match ty {
+            Some(ty) => Expr::annotated_abs(var, ty, body),
+            None => Expr::abs(var, body),
         };
+        Ok(abs.with_span(self.span_from(start)))
+    }
+
+    /// Whether the next token can start the argument of an application.
+    fn at_argument(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(
+                Token::Identifier(_)
+                    | Token::Number(_)
+                    | Token::Keyword(Keyword::True | Keyword::False)
+                    | Token::Parentheses(Paren::Open)
+                    | Token::Lambda
+            )
+        )
     }
 
-    // Mtching variables names and literal values
+    fn application(&mut self) -> Result<Expr, SyntaxError> {
+        let start = self.position;
+        let mut function = self.binary(0)?;
+        while self.at_argument() {
+            let parameter = self.binary(0)?;
+            function = Expr::app(function, parameter).with_span(self.span_from(start));
+        }
+        Ok(function)
+    }
 
-    match tokens {
-        [Token::Identifier(first), Token::Dot, Token::Identifier(second)] => {
-            match format!("{first}.{second}").parse::<f64>() {
-                Ok(n) => Ok(Expr::numeric_literal(n)),
-                _ => Err(SyntaxError::new(tokens[0].clone())),
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr, SyntaxError> {
+        let start = self.position;
+        let mut lhs = self.unary()?;
+        while let Some(Token::Operator(op)) = self.peek() {
+            match BinaryOp::from_token(op) {
+                Some(operation) if operation.precedence() >= min_precedence => {
+                    self.next();
+                    let rhs = self.binary(operation.precedence() + 1)?;
+                    lhs = operation.apply(lhs, rhs).with_span(self.span_from(start));
+                }
+                _ => break,
             }
         }
-        [Token::Identifier(id), Token::Dot] => match id.parse::<f64>() {
-            Ok(n) => Ok(Expr::numeric_literal(n)),
-            _ => Err(SyntaxError::new(tokens[0].clone())),
-        },
-        [Token::Identifier(id)] if *id == "true" => Ok(Expr::boolean_literal(true)),
-        [Token::Identifier(id)] if *id == "false" => Ok(Expr::boolean_literal(false)),
-        [Token::Identifier(id)] if id.starts_with(|c: char| c.is_alphabetic()) => Ok(Expr::var(id)),
-        [Token::Identifier(id)] => {
-            if let Ok(n) = id.parse::<f64>() {
-                Ok(Expr::numeric_literal(n))
-            } else {
-                Err(SyntaxError::new(tokens[0].clone()))
+        Ok(lhs)
+    }
+
+    /// Prefix operators bind tighter than binary ones: `-x * y` is `(-x) * y`.
+    fn unary(&mut self) -> Result<Expr, SyntaxError> {
+        let operation = match self.peek() {
+            Some(Token::Operator(Op::Minus)) => UnaryOp::Neg,
+            Some(Token::Operator(Op::Not)) => UnaryOp::Not,
+            _ => return self.atom(),
+        };
+        let start = self.position;
+        self.next();
+        let operand = self.unary()?;
+        Ok(Expr::unary(operation, operand).with_span(self.span_from(start)))
+    }
+
+    fn atom(&mut self) -> Result<Expr, SyntaxError> {
+        let Some(token) = self.tokens.get(self.position) else {
+            return Err(self.unexpected());
+        };
+        match &token.value {
+            Token::Lambda => self.abstraction(),
+            Token::Parentheses(Paren::Open) => {
+                let start = self.position;
+                self.next();
+                let inner = self.expr().and_then(|expr| {
+                    self.expect(Token::Parentheses(Paren::Close))?;
+                    Ok(expr)
+                });
+                match inner {
+                    Ok(expr) => Ok(expr),
+                    Err(error) => {
+                        self.errors.push(error);
+                        self.synchronize_parentheses();
+                        Ok(Expr::error().with_span(self.span_from(start)))
+                    }
+                }
+            }
+            Token::Identifier(id) => {
+                self.next();
+                Ok(Expr::var(id).with_span(token.span))
             }
+            Token::Keyword(keyword @ (Keyword::True | Keyword::False)) => {
+                self.next();
+                Ok(Expr::boolean_literal(*keyword == Keyword::True).with_span(token.span))
+            }
+            Token::Number(value) => {
+                self.next();
+                Ok(Expr::numeric_literal(*value).with_span(token.span))
+            }
+            // Tokens closing an expression mean that the expression before them is empty
+            Token::Parentheses(Paren::Close)
+            | Token::Colon
+            | Token::QuestionMark
+            | Token::Semicolon => Err(SyntaxError::missing_expression(self.end_span())),
+            _ => Err(self.unexpected()),
         }
-        _ => Err(SyntaxError::new(tokens[0].clone())),
     }
+}
 
-    // Ok(Expr::Var("a"))
+/// The outcome of parsing with error recovery.
+#[derive(Debug)]
+pub struct Parsed<T> {
+    /// The syntax tree, where the parts that could not be parsed are `ExprKind::Error` nodes.
+    pub tree: T,
+    /// Every syntax error found, in the order they appear in the source.
+    pub errors: Vec<SyntaxError>,
 }
 
-fn remove_extra_parentheses(tokens: &[Token]) -> &[Token] {
-    if tokens.len() == 0 {
-        return tokens;
+impl<T> Parsed<T> {
+    fn into_result(self) -> Result<T, Vec<SyntaxError>> {
+        if self.errors.is_empty() {
+            Ok(self.tree)
+        } else {
+            Err(self.errors)
+        }
     }
-    if *tokens.iter().next().unwrap() == Token::Parentheses(Paren::Open)
-        && *tokens.iter().last().unwrap() == Token::Parentheses(Paren::Close)
-    {
-        let mut depth = 0;
-        for ref token in &tokens[..tokens.len() - 1] {
-            if **token == Token::Parentheses(Paren::Open) {
-                depth += 1;
-            } else if **token == Token::Parentheses(Paren::Close) {
-                depth -= 1;
-            }
-            if depth == 0 {
-                return tokens;
-            }
+}
+
+/// Parses a single expression, recovering from syntax errors to report all of them.
+pub fn parse_with_recovery(tokens: &[Spanned<Token>]) -> Parsed<Expr> {
+    let mut parser = Parser::new(tokens);
+    let expr = match parser.expr() {
+        Ok(expr) => expr,
+        Err(error) => {
+            parser.errors.push(error);
+            parser.position = tokens.len();
+            Expr::error().with_span(parser.span_from(0))
         }
-        return remove_extra_parentheses(&tokens[1..tokens.len() - 1]);
-    } else {
-        return tokens;
+    };
+    if parser.position < tokens.len() {
+        let error = parser.unexpected();
+        parser.errors.push(error);
+    }
+    Parsed {
+        tree: expr,
+        errors: parser.errors,
     }
 }
 
+/// Parses a single expression, failing with every syntax error found if there is any.
+pub fn parse(tokens: &[Spanned<Token>]) -> Result<Expr, Vec<SyntaxError>> {
+    parse_with_recovery(tokens).into_result()
+}
+
+/// Parses a program made of definitions and expressions, recovering from syntax errors.
+pub fn parse_program_with_recovery(tokens: &[Spanned<Token>]) -> Parsed<Program> {
+    let mut parser = Parser::new(tokens);
+    let program = parser.program();
+    Parsed {
+        tree: program,
+        errors: parser.errors,
+    }
+}
+
+/// Parses a program, failing with every syntax error found if there is any.
+pub fn parse_program(tokens: &[Spanned<Token>]) -> Result<Program, Vec<SyntaxError>> {
+    parse_program_with_recovery(tokens).into_result()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::parser::*;
+    use crate::{lexer::lexer, parser::*};
+
+    fn spanned(tokens: Vec<Token>) -> Vec<Spanned<Token>> {
+        tokens
+            .into_iter()
+            .map(|token| Spanned::new(token, Span::default()))
+            .collect()
+    }
+
     #[test]
     fn test_simple() {
         assert_eq!(
-            parse(&vec![
+            parse(&spanned(vec![
                 Token::Lambda,
                 Token::identifier("x"),
                 Token::Dot,
                 Token::identifier("x")
-            ]),
+            ])),
             Ok(Expr::abs("x", Expr::var("x")))
         );
         assert_eq!(
-            parse(&vec![
+            parse(&spanned(vec![
                 Token::Parentheses(Paren::Open),
                 Token::Lambda,
                 Token::identifier("x"),
                 Token::Dot,
                 Token::identifier("x"),
                 Token::Parentheses(Paren::Close),
-                Token::identifier("1")
-            ]),
+                Token::Number(1.)
+            ])),
             Ok(Expr::app(
                 Expr::abs("x", Expr::var("x")),
                 Expr::numeric_literal(1.)
             ))
         );
+        assert_eq!(
+            parse_str(r"\x'.f_1 x' true"),
+            Ok(Expr::abs(
+                "x'",
+                Expr::app(
+                    Expr::app(Expr::var("f_1"), Expr::var("x'")),
+                    Expr::boolean_literal(true)
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let expr = parse(&lexer(r"(\x.x+1) 12").unwrap()).unwrap();
+        assert_eq!((expr.span.start.offset, expr.span.end.offset), (0, 11));
+        let ExprKind::App {
+            function,
+            parameter,
+        } = expr.kind
+        else {
+            panic!("expected an application, got {:?}", expr);
+        };
+        assert_eq!(
+            (function.span.start.offset, function.span.end.offset),
+            (1, 7)
+        );
+        assert_eq!(
+            (parameter.span.start.offset, parameter.span.end.offset),
+            (9, 11)
+        );
+    }
+
+    #[test]
+    fn test_error_position() {
+        let [SyntaxError::MissingExpression { span }] = errors(r"(\x.) 1")[..] else {
+            panic!("expected a missing expression");
+        };
+        assert_eq!(span.start.column, 5);
+    }
+
+    fn parse_str(prog: &str) -> Result<Expr, Vec<SyntaxError>> {
+        parse(&lexer(prog).unwrap())
+    }
+
+    fn errors(prog: &str) -> Vec<SyntaxError> {
+        parse_str(prog).unwrap_err()
+    }
+
+    #[test]
+    fn test_precedence() {
+        use ArithmeticOp::*;
+        use ComparisonOp::*;
+        let (a, b, c, d) = (
+            Expr::var("a"),
+            Expr::var("b"),
+            Expr::var("c"),
+            Expr::var("d"),
+        );
+        assert_eq!(
+            parse_str("a+b*c-d"),
+            Ok(Expr::arithmetic(
+                Sub,
+                Expr::arithmetic(Add, a.clone(), Expr::arithmetic(Mul, b.clone(), c.clone())),
+                d.clone()
+            ))
+        );
+        assert_eq!(
+            parse_str("a=b!=c"),
+            Ok(Expr::comparison(
+                Neq,
+                Expr::comparison(Eq, a.clone(), b.clone()),
+                c.clone()
+            ))
+        );
+        assert_eq!(
+            parse_str("a||b&&c=d"),
+            Ok(Expr::logical(
+                LogicalOp::Or,
+                a.clone(),
+                Expr::logical(
+                    LogicalOp::And,
+                    b.clone(),
+                    Expr::comparison(Eq, c.clone(), d.clone())
+                )
+            ))
+        );
+        assert_eq!(
+            parse_str("a<b+c"),
+            Ok(Expr::comparison(
+                Lt,
+                a.clone(),
+                Expr::arithmetic(Add, b.clone(), c.clone())
+            ))
+        );
+        assert_eq!(
+            parse_str("a b c"),
+            Ok(Expr::app(Expr::app(a.clone(), b.clone()), c.clone()))
+        );
+        assert_eq!(
+            parse_str("a x-1"),
+            Ok(Expr::app(
+                a.clone(),
+                Expr::arithmetic(Sub, Expr::var("x"), Expr::numeric_literal(1.))
+            ))
+        );
+        assert_eq!(
+            parse_str("a?b:c d"),
+            Ok(Expr::conditional(
+                a.clone(),
+                b.clone(),
+                Expr::app(c.clone(), d.clone())
+            ))
+        );
+        assert_eq!(
+            parse_str(r"\f.\x.f x"),
+            Ok(Expr::abs(
+                "f",
+                Expr::abs("x", Expr::app(Expr::var("f"), Expr::var("x")))
+            ))
+        );
+        assert_eq!(
+            parse_str(r"a + \x.x + 1"),
+            Ok(Expr::arithmetic(
+                Add,
+                a,
+                Expr::abs(
+                    "x",
+                    Expr::arithmetic(Add, Expr::var("x"), Expr::numeric_literal(1.))
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_unary() {
+        use ArithmeticOp::*;
+        let (x, y) = (Expr::var("x"), Expr::var("y"));
+        let neg = |expr| Expr::unary(UnaryOp::Neg, expr);
+        assert_eq!(
+            parse_str("-x*2"),
+            Ok(Expr::arithmetic(
+                Mul,
+                neg(x.clone()),
+                Expr::numeric_literal(2.)
+            ))
+        );
+        assert_eq!(
+            parse_str("x - -y"),
+            Ok(Expr::arithmetic(Sub, x.clone(), neg(y.clone())))
+        );
+        assert_eq!(parse_str("- -x"), Ok(neg(neg(x.clone()))));
+        assert_eq!(
+            parse_str("!x = y"),
+            Ok(Expr::comparison(
+                ComparisonOp::Eq,
+                Expr::unary(UnaryOp::Not, x.clone()),
+                y.clone()
+            ))
+        );
+        // A `-` after a function is a subtraction
+        assert_eq!(
+            parse_str("f -x"),
+            Ok(Expr::arithmetic(Sub, Expr::var("f"), x.clone()))
+        );
+        assert_eq!(
+            parse_str("f (-x) y"),
+            Ok(Expr::app(Expr::app(Expr::var("f"), neg(x)), y))
+        );
+        assert!(matches!(
+            errors("1 + -")[..],
+            [SyntaxError::MissingExpression { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse_str("2.5"), Ok(Expr::numeric_literal(2.5)));
+        assert_eq!(
+            parse_str("f 1e3 0xff"),
+            Ok(Expr::app(
+                Expr::app(Expr::var("f"), Expr::numeric_literal(1000.)),
+                Expr::numeric_literal(255.)
+            ))
+        );
+        assert_eq!(
+            parse_str("-2.5"),
+            Ok(Expr::unary(UnaryOp::Neg, Expr::numeric_literal(2.5)))
+        );
+        assert_eq!(
+            parse_str(r"\x.2.5"),
+            Ok(Expr::abs("x", Expr::numeric_literal(2.5)))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            errors("(1+2")[..],
+            [SyntaxError::ExpectedToken { .. }]
+        ));
+        assert!(matches!(
+            errors("a ? b")[..],
+            [SyntaxError::ExpectedToken { .. }]
+        ));
+        assert!(matches!(
+            errors("1 +")[..],
+            [SyntaxError::MissingExpression { .. }]
+        ));
+        assert!(matches!(
+            errors("1 )")[..],
+            [SyntaxError::IllegalToken {
+                token: Token::Parentheses(Paren::Close),
+                ..
+            }]
+        ));
+        // Keywords can't be bound
+        for program in [r"\in.1", "let true = 1", r"let rec let = \x.x in 1"] {
+            assert!(
+                matches!(errors(program)[..], [SyntaxError::ExpectedToken { .. }]),
+                "{}",
+                program
+            );
+        }
+        assert!(matches!(
+            errors("1 + rec")[..],
+            [SyntaxError::IllegalToken {
+                token: Token::Keyword(Keyword::Rec),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_long_program() {
+        let prog = vec!["1"; 10_000].join("+");
+        assert!(parse_str(&prog).is_ok());
+    }
+
+    #[test]
+    fn test_recovery() {
+        let Parsed { tree: expr, errors } =
+            parse_with_recovery(&lexer(r"(1 +) + f (\x.) (2 : 3) + 4").unwrap());
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[0], SyntaxError::MissingExpression { .. }));
+        assert!(matches!(errors[1], SyntaxError::MissingExpression { .. }));
+        assert!(matches!(errors[2], SyntaxError::ExpectedToken { .. }));
+        assert_eq!(
+            expr,
+            Expr::app(
+                Expr::app(
+                    Expr::arithmetic(ArithmeticOp::Add, Expr::error(), Expr::var("f")),
+                    Expr::error()
+                ),
+                Expr::arithmetic(ArithmeticOp::Add, Expr::error(), Expr::numeric_literal(4.))
+            )
+        );
+    }
+
+    #[test]
+    fn test_program() {
+        let program = parse_program(&lexer(r"let id = \x.x; let one = id 1; one + 1;").unwrap());
+        assert_eq!(
+            program,
+            Ok(Program {
+                items: vec![
+                    Item::Definition {
+                        name: "id".into(),
+                        value: Expr::abs("x", Expr::var("x")),
+                    },
+                    Item::Definition {
+                        name: "one".into(),
+                        value: Expr::app(Expr::var("id"), Expr::numeric_literal(1.)),
+                    },
+                    Item::Expression(Expr::arithmetic(
+                        ArithmeticOp::Add,
+                        Expr::var("one"),
+                        Expr::numeric_literal(1.)
+                    )),
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_program_recovery() {
+        let Parsed { tree, errors } =
+            parse_program_with_recovery(&lexer(r"let = 1; let x = ; x 1 ); 2").unwrap());
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[0], SyntaxError::ExpectedToken { .. }));
+        assert!(matches!(errors[1], SyntaxError::MissingExpression { .. }));
+        assert!(matches!(errors[2], SyntaxError::IllegalToken { .. }));
+        assert_eq!(
+            tree.items,
+            vec![
+                Item::Expression(Expr::app(Expr::var("x"), Expr::numeric_literal(1.))),
+                Item::Expression(Expr::numeric_literal(2.))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_let_rec() {
+        let f = Expr::abs("x", Expr::app(Expr::var("f"), Expr::var("x")));
+        assert_eq!(
+            parse_str(r"let rec f = \x.f x in f 1"),
+            Ok(Expr::let_rec(
+                "f",
+                f.clone(),
+                Expr::app(Expr::var("f"), Expr::numeric_literal(1.))
+            ))
+        );
+        assert_eq!(
+            parse_program(&lexer(r"let rec f = \x.f x").unwrap()).map(|program| program.items),
+            Ok(vec![Item::Definition {
+                name: "f".into(),
+                value: Expr::let_rec("f", f, Expr::var("f"))
+            }])
+        );
+        assert!(matches!(
+            errors(r"let rec f = 1 in f")[..],
+            [SyntaxError::ExpectedToken { .. }]
+        ));
+        assert!(matches!(
+            errors(r"let f = \x.x in f")[..],
+            [SyntaxError::ExpectedToken { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_annotations() {
+        let num_to_num = Type::function(Type::Num, Type::Num);
+        assert_eq!(
+            parse_str(r"\f: (Num -> Num) -> Bool.\x: Num.f"),
+            Ok(Expr::annotated_abs(
+                "f",
+                Type::function(num_to_num.clone(), Type::Bool),
+                Expr::annotated_abs("x", Type::Num, Expr::var("f"))
+            ))
+        );
+        assert_eq!(
+            parse_str(r"\f: Num -> Num -> Num.f"),
+            Ok(Expr::annotated_abs(
+                "f",
+                Type::function(Type::Num, num_to_num.clone()),
+                Expr::var("f")
+            ))
+        );
+        let f = Expr::annotated_abs("x", Type::Num, Expr::app(Expr::var("f"), Expr::var("x")));
+        assert_eq!(
+            parse_str(r"let rec f: Num -> Num = \x: Num.f x in f"),
+            Ok(Expr::annotated_let_rec(
+                "f",
+                num_to_num.clone(),
+                f.clone(),
+                Expr::var("f")
+            ))
+        );
+        assert_eq!(
+            parse_program(&lexer(r"let rec f: Num -> Num = \x: Num.f x").unwrap())
+                .map(|program| program.items),
+            Ok(vec![Item::Definition {
+                name: "f".into(),
+                value: Expr::annotated_let_rec("f", num_to_num, f, Expr::var("f"))
+            }])
+        );
+        for program in [r"\x: Int.x", r"\x:.x", r"\x: (Num.x", r"\x: Num ->.x"] {
+            assert!(
+                matches!(errors(program)[..], [SyntaxError::ExpectedToken { .. }]),
+                "{}",
+                program
+            );
+        }
     }
 }
@@ -0,0 +1,765 @@
+use std::ops::Range;
+
+use crate::{
+    ast::{ArithmeticOp, ComparisonOp, Expr, ExprKind, Item, Program, UnaryOp},
+    types::Type,
+};
+
+/// A layout of the text, which can be broken over several lines where it has `Line`s.
+enum Doc {
+    Text(String),
+    /// Written as the given text when the enclosing group fits on the line,
+    /// otherwise as a new line.
+    Line(&'static str),
+    /// Indents the lines broken inside the document.
+    Nest(usize, Box<Doc>),
+    /// Breaks either all the lines directly inside it or none of them.
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+    /// The document of the part whose position is recorded.
+    Part(Box<Doc>),
+}
+
+fn text(text: impl Into<String>) -> Doc {
+    Doc::Text(text.into())
+}
+
+fn nest(doc: Doc) -> Doc {
+    Doc::Nest(INDENT, Box::new(doc))
+}
+
+fn group(docs: Vec<Doc>) -> Doc {
+    Doc::Group(Box::new(Doc::Concat(docs)))
+}
+
+const INDENT: usize = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+enum Command<'a> {
+    Doc(usize, Mode, &'a Doc),
+    /// The end of the part that started at this offset.
+    EndPart(usize),
+}
+
+/// Whether the commands, up to the first broken line, fit in `width` columns.
+fn fits(mut width: usize, commands: &[Command]) -> bool {
+    let mut stack: Vec<(Mode, &Doc)> = vec![];
+    let mut commands = commands.iter().rev();
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match commands.next() {
+                Some(Command::Doc(_, mode, doc)) => (*mode, *doc),
+                Some(Command::EndPart(_)) => continue,
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => match width.checked_sub(text.chars().count()) {
+                Some(left) => width = left,
+                None => return false,
+            },
+            Doc::Line(_) if mode == Mode::Break => return true,
+            Doc::Line(flat) => match width.checked_sub(flat.len()) {
+                Some(left) => width = left,
+                None => return false,
+            },
+            Doc::Nest(_, doc) | Doc::Part(doc) => stack.push((mode, doc)),
+            Doc::Group(doc) => stack.push((Mode::Flat, doc)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+        }
+    }
+}
+
+/// Writes `doc` in `width` columns, returning where its `Part` was written.
+fn render(doc: &Doc, width: usize) -> (String, Option<Range<usize>>) {
+    let mut out = String::new();
+    let mut part = None;
+    let mut column = 0;
+    let mut commands = vec![Command::Doc(0, Mode::Break, doc)];
+    while let Some(command) = commands.pop() {
+        let (indent, mode, doc) = match command {
+            Command::Doc(indent, mode, doc) => (indent, mode, doc),
+            Command::EndPart(start) => {
+                part = Some(start..out.len());
+                continue;
+            }
+        };
+        match doc {
+            Doc::Text(text) => {
+                out += text;
+                column += text.chars().count();
+            }
+            Doc::Line(flat) if mode == Mode::Flat => {
+                out += flat;
+                column += flat.len();
+            }
+            Doc::Line(_) => {
+                out.push('\n');
+                out += &" ".repeat(indent);
+                column = indent;
+            }
+            Doc::Nest(more, doc) => commands.push(Command::Doc(indent + more, mode, doc)),
+            Doc::Group(doc) => {
+                let flat = Command::Doc(indent, Mode::Flat, doc);
+                let fits_on_line = mode == Mode::Flat || {
+                    commands.push(flat);
+                    let fits = fits(width.saturating_sub(column), &commands);
+                    commands.pop();
+                    fits
+                };
+                let mode = if fits_on_line {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                commands.push(Command::Doc(indent, mode, doc));
+            }
+            Doc::Concat(docs) => {
+                commands.extend(docs.iter().rev().map(|doc| Command::Doc(indent, mode, doc)))
+            }
+            Doc::Part(doc) => {
+                commands.push(Command::EndPart(out.len()));
+                commands.push(Command::Doc(indent, mode, doc));
+            }
+        }
+    }
+    (out, part)
+}
+
+/// How tightly the context of an expression binds, see the grammar of the parser.
+///
+/// Abstractions, `let rec` and conditionals are expressions, the operands of
+/// applications are binary operations, binary operators bind tighter the higher
+/// their precedence and prefix operators bind tighter than all of them.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Level {
+    Expr,
+    Application,
+    Binary(u8),
+    Unary,
+    Atom,
+}
+
+/// Where an expression is written.
+#[derive(Clone, Copy)]
+struct Context {
+    /// The loosest expression that can be written without parentheses.
+    level: Level,
+    /// Whether nothing follows the expression before the end of its enclosing
+    /// expression, so that an abstraction can extend to the right.
+    last: bool,
+}
+
+impl Context {
+    const TOP: Context = Context {
+        level: Level::Expr,
+        last: true,
+    };
+
+    fn new(level: Level, last: bool) -> Self {
+        Self { level, last }
+    }
+}
+
+fn level(expr: &Expr) -> Level {
+    match &expr.kind {
+        ExprKind::Abs { .. } | ExprKind::LetRec { .. } | ExprKind::Conditional { .. } => {
+            Level::Expr
+        }
+        ExprKind::App { .. } => Level::Application,
+        ExprKind::Arithmetic { operation, .. } => Level::Binary(operation.precedence()),
+        ExprKind::Comparison { operation, .. } => Level::Binary(operation.precedence()),
+        ExprKind::Logical { operation, .. } => Level::Binary(operation.precedence()),
+        ExprKind::Unary { .. } => Level::Unary,
+        ExprKind::NumericLiteral { value } if value.is_nan() || *value == f64::INFINITY => {
+            Level::Binary(ArithmeticOp::Div.precedence())
+        }
+        // Written with a `-`, so read back as a negation
+        ExprKind::NumericLiteral { value } if value.is_sign_negative() => Level::Unary,
+        ExprKind::Var { .. }
+        | ExprKind::NumericLiteral { .. }
+        | ExprKind::BooleanLiteral { .. }
+        | ExprKind::Error => Level::Atom,
+    }
+}
+
+/// The prefix operator `expr` is written with first, if any. An argument can't
+/// start with one: `f -x` is a subtraction.
+fn leading_operator(expr: &Expr) -> Option<UnaryOp> {
+    match &expr.kind {
+        ExprKind::Unary { operation, .. } => Some(operation.clone()),
+        ExprKind::NumericLiteral { value } if !value.is_nan() && value.is_sign_negative() => {
+            Some(UnaryOp::Neg)
+        }
+        ExprKind::Arithmetic { lhs, .. }
+        | ExprKind::Comparison { lhs, .. }
+        | ExprKind::Logical { lhs, .. }
+            if level(lhs) >= level(expr) =>
+        {
+            leading_operator(lhs)
+        }
+        _ => None,
+    }
+}
+
+/// How `value` is written. There are no literals for the values that are not
+/// finite, so they are written as the divisions giving them.
+fn number(value: f64) -> String {
+    if value.is_nan() {
+        "0 / 0".to_owned()
+    } else if value == f64::INFINITY {
+        "1 / 0".to_owned()
+    } else if value == f64::NEG_INFINITY {
+        "-(1 / 0)".to_owned()
+    } else {
+        value.to_string()
+    }
+}
+
+/// The characters used for the symbols that have two spellings.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Symbols {
+    /// `\`, `<=`, `>=`, `!=` and `->`.
+    #[default]
+    Ascii,
+    /// `λ`, `≤`, `≥`, `≠` and `→`.
+    Unicode,
+}
+
+/// Writes expressions in the syntax of the language with as few parentheses
+/// as possible, breaking lines that are longer than a given width.
+#[derive(Debug, Clone, Copy)]
+pub struct Printer {
+    width: usize,
+    symbols: Symbols,
+}
+
+impl Default for Printer {
+    /// Writes everything on a single line, in ASCII.
+    fn default() -> Self {
+        Self {
+            width: usize::MAX,
+            symbols: Symbols::Ascii,
+        }
+    }
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Breaks the lines longer than `width` columns, when possible.
+    pub fn with_width(self, width: usize) -> Self {
+        Self { width, ..self }
+    }
+
+    pub fn with_symbols(self, symbols: Symbols) -> Self {
+        Self { symbols, ..self }
+    }
+
+    pub fn print(&self, expr: &Expr) -> String {
+        render(&self.doc(expr, Context::TOP, None), self.width).0
+    }
+
+    /// Writes `expr` and returns where `part`, one of its sub-expressions compared
+    /// by address, is in the text, without its parentheses.
+    pub fn print_locating(&self, expr: &Expr, part: &Expr) -> (String, Option<Range<usize>>) {
+        render(&self.doc(expr, Context::TOP, Some(part)), self.width)
+    }
+
+    pub fn print_item(&self, item: &Item) -> String {
+        render(&self.item_doc(item), self.width).0
+    }
+
+    /// Writes a type like `Type`'s `Display`, with the arrows of the symbols.
+    pub fn print_type(&self, ty: &Type) -> String {
+        let arrow = match self.symbols {
+            Symbols::Ascii => "->",
+            Symbols::Unicode => "→",
+        };
+        match ty {
+            Type::Function { parameter, result } => {
+                let parameter = match **parameter {
+                    Type::Function { .. } => format!("({})", self.print_type(parameter)),
+                    _ => self.print_type(parameter),
+                };
+                format!("{} {} {}", parameter, arrow, self.print_type(result))
+            }
+            _ => ty.to_string(),
+        }
+    }
+
+    /// Writes the items of `program` separated by `;`.
+    pub fn print_program(&self, program: &Program) -> String {
+        let items: Vec<String> = program
+            .items
+            .iter()
+            .map(|item| self.print_item(item))
+            .collect();
+        items.join("; ")
+    }
+
+    /// A bound name with its type, if written.
+    fn binder(&self, name: &str, ty: &Option<Type>) -> String {
+        match ty {
+            Some(ty) => format!("{}: {}", name, self.print_type(ty)),
+            None => name.to_owned(),
+        }
+    }
+
+    fn lambda(&self) -> &'static str {
+        match self.symbols {
+            Symbols::Ascii => "\\",
+            Symbols::Unicode => "λ",
+        }
+    }
+
+    fn comparison(&self, operation: &ComparisonOp) -> String {
+        match (self.symbols, operation) {
+            (Symbols::Unicode, ComparisonOp::Gte) => "≥".to_owned(),
+            (Symbols::Unicode, ComparisonOp::Lte) => "≤".to_owned(),
+            (Symbols::Unicode, ComparisonOp::Neq) => "≠".to_owned(),
+            _ => operation.to_string(),
+        }
+    }
+
+    fn item_doc(&self, item: &Item) -> Doc {
+        match item {
+            Item::Definition { name, value } => match &value.kind {
+                // `let rec f = value` is parsed as `let f = let rec f = value in f`
+                ExprKind::LetRec {
+                    name: inner,
+                    ty,
+                    value,
+                    body,
+                } if inner == name
+                    && matches!(&body.kind, ExprKind::Var { name: var } if var == name) =>
+                {
+                    group(vec![
+                        text(format!("let rec {} =", self.binder(name, ty))),
+                        nest(Doc::Concat(vec![
+                            Doc::Line(" "),
+                            self.doc(value, Context::TOP, None),
+                        ])),
+                    ])
+                }
+                _ => group(vec![
+                    text(format!("let {} =", name)),
+                    nest(Doc::Concat(vec![
+                        Doc::Line(" "),
+                        self.doc(value, Context::TOP, None),
+                    ])),
+                ]),
+            },
+            Item::Expression(expr) => self.doc(expr, Context::TOP, None),
+        }
+    }
+
+    fn doc(&self, expr: &Expr, context: Context, part: Option<&Expr>) -> Doc {
+        let parenthesized = match expr.kind {
+            ExprKind::Abs { .. } => !context.last,
+            _ => level(expr) < context.level || (level(expr) == Level::Expr && !context.last),
+        };
+        let context = if parenthesized { Context::TOP } else { context };
+        let mut doc = self.unparenthesized(expr, context, part);
+        if part.is_some_and(|part| std::ptr::eq(part, expr)) {
+            doc = Doc::Part(Box::new(doc));
+        }
+        if parenthesized {
+            Doc::Concat(vec![text("("), Doc::Nest(1, Box::new(doc)), text(")")])
+        } else {
+            doc
+        }
+    }
+
+    fn unparenthesized(&self, expr: &Expr, context: Context, part: Option<&Expr>) -> Doc {
+        let doc = |expr: &Expr, context: Context| self.doc(expr, context, part);
+        // Binary operators are left associative
+        let binary = |operator: String, precedence: u8, lhs: &Expr, rhs: &Expr| {
+            group(vec![
+                doc(lhs, Context::new(Level::Binary(precedence), false)),
+                nest(Doc::Concat(vec![
+                    Doc::Line(" "),
+                    text(format!("{} ", operator)),
+                    doc(
+                        rhs,
+                        Context::new(Level::Binary(precedence + 1), context.last),
+                    ),
+                ])),
+            ])
+        };
+        match &expr.kind {
+            ExprKind::Abs { var, ty, body } => {
+                // Curried functions are written as a single header `\x.\y.`
+                let mut header = format!("{}{}.", self.lambda(), self.binder(var, ty));
+                let mut body = body;
+                while let ExprKind::Abs {
+                    var,
+                    ty,
+                    body: inner,
+                } = &body.kind
+                {
+                    if part.is_some_and(|part| std::ptr::eq(part, &**body)) {
+                        break;
+                    }
+                    header += &format!("{}{}.", self.lambda(), self.binder(var, ty));
+                    body = inner;
+                }
+                group(vec![
+                    text(header),
+                    nest(Doc::Concat(vec![Doc::Line(""), doc(body, Context::TOP)])),
+                ])
+            }
+            ExprKind::App {
+                function,
+                parameter,
+            } => {
+                let level = if leading_operator(parameter).is_some() {
+                    Level::Atom
+                } else {
+                    Level::Binary(0)
+                };
+                group(vec![
+                    doc(function, Context::new(Level::Application, false)),
+                    nest(Doc::Concat(vec![
+                        Doc::Line(" "),
+                        doc(parameter, Context::new(level, context.last)),
+                    ])),
+                ])
+            }
+            ExprKind::Arithmetic {
+                operation,
+                lhs,
+                rhs,
+            } => binary(operation.to_string(), operation.precedence(), lhs, rhs),
+            ExprKind::Comparison {
+                operation,
+                lhs,
+                rhs,
+            } => binary(self.comparison(operation), operation.precedence(), lhs, rhs),
+            ExprKind::Logical {
+                operation,
+                lhs,
+                rhs,
+            } => binary(operation.to_string(), operation.precedence(), lhs, rhs),
+            ExprKind::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => group(vec![
+                doc(condition, Context::new(Level::Application, false)),
+                nest(Doc::Concat(vec![
+                    Doc::Line(" "),
+                    text("? "),
+                    doc(true_branch, Context::TOP),
+                    Doc::Line(" "),
+                    text(": "),
+                    doc(false_branch, Context::new(Level::Expr, context.last)),
+                ])),
+            ]),
+            ExprKind::Unary { operation, operand } => {
+                // `- -x`, as `--` starts a comment
+                let separator = match (operation, leading_operator(operand)) {
+                    (UnaryOp::Neg, Some(UnaryOp::Neg)) => " ",
+                    _ => "",
+                };
+                Doc::Concat(vec![
+                    text(format!("{}{}", operation, separator)),
+                    doc(operand, Context::new(Level::Unary, context.last)),
+                ])
+            }
+            ExprKind::Var { name } => text(&**name),
+            ExprKind::NumericLiteral { value } => text(number(*value)),
+            ExprKind::BooleanLiteral { value } => text(value.to_string()),
+            ExprKind::LetRec {
+                name,
+                ty,
+                value,
+                body,
+            } => group(vec![
+                text(format!("let rec {} =", self.binder(name, ty))),
+                nest(Doc::Concat(vec![Doc::Line(" "), doc(value, Context::TOP)])),
+                Doc::Line(" "),
+                text("in "),
+                doc(body, Context::new(Level::Expr, context.last)),
+            ]),
+            ExprKind::Error => text("<error>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ast::{ArithmeticOp, ComparisonOp, Expr, LogicalOp, UnaryOp},
+        interpreter::EvalResult,
+        lexer::lexer,
+        parser::{parse, parse_program},
+        printer::*,
+    };
+
+    fn reprint(program: &str) -> String {
+        parse(&lexer(program).unwrap()).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_minimal_parentheses() {
+        for program in [
+            r"(\x.x) 1",
+            r"f (\x.x) \y.y",
+            r"a - (b - c) + d * e",
+            r"(a + b) * c",
+            r"f x + 1",
+            r"(f x) + 1",
+            r"(a ? b : c) ? d : e ? f : g",
+            r"(\x.x) + \y.y",
+            r"a * (\x.x) + b",
+            r"f (let rec g = \x.g x in g) 1",
+            r"\x.\y.x = y ? true : false",
+            r"let rec f = \x.f x in f 2.5",
+            r"\f: (Num -> Bool) -> Num.\x: Num.f x",
+            r"let rec f: Num -> Num = \x: Num.f x in f",
+            r"-x * 2 - - -y",
+            r"f (-x) (-x + 1) (!b)",
+            r"-(f x) y",
+            r"!(a < b) = !c",
+            r"(a || b) && c < d || !e",
+            r"(f a) && !b",
+        ] {
+            assert_eq!(reprint(program), program);
+        }
+        assert_eq!(reprint(r"((\x.(x)) ((1)))"), r"(\x.x) 1");
+        assert_eq!(reprint(r"(a*b)+(c*d)"), r"a * b + c * d");
+        assert_eq!(reprint(r"f (g x)"), r"f (g x)");
+        assert_eq!(reprint(r"\x:Num->(Num->Num).x"), r"\x: Num -> Num -> Num.x");
+        assert_eq!(reprint(r"(-x) * (!y)"), r"-x * !y");
+        let negative = Expr::app(Expr::var("f"), Expr::numeric_literal(-1.));
+        assert_eq!(negative.to_string(), "f (-1)");
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        let closure = Expr::abs("y", Expr::numeric_literal(f64::INFINITY));
+        assert_eq!(closure.to_string(), r"\y.1 / 0");
+        let negative = Expr::app(Expr::var("f"), Expr::numeric_literal(f64::NEG_INFINITY));
+        assert_eq!(negative.to_string(), "f (-(1 / 0))");
+        let product = Expr::arithmetic(
+            ArithmeticOp::Mul,
+            Expr::var("x"),
+            Expr::numeric_literal(f64::NAN),
+        );
+        assert_eq!(product.to_string(), "x * (0 / 0)");
+        for value in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            let printed = Expr::numeric_literal(value).to_string();
+            let parsed = parse(&lexer(&printed).unwrap()).unwrap();
+            let Ok(EvalResult::Value(read)) = parsed.eval() else {
+                panic!("{} is not a number", printed);
+            };
+            assert!(
+                read == value || read.is_nan() && value.is_nan(),
+                "{}",
+                printed
+            );
+        }
+    }
+
+    #[test]
+    fn test_symbols() {
+        let unicode = Printer::new().with_symbols(Symbols::Unicode);
+        let expr = parse(&lexer(r"\f: Num -> Num -> Bool.\x.f x 1 != (x >= 2)").unwrap()).unwrap();
+        assert_eq!(
+            unicode.print(&expr),
+            r"λf: Num → Num → Bool.λx.f x 1 ≠ (x ≥ 2)"
+        );
+        let program =
+            parse_program(&lexer(r"let rec f: Num -> Num = \x.x <= 1 ? 1 : f x-1").unwrap())
+                .unwrap();
+        assert_eq!(
+            unicode.print_item(&program.items[0]),
+            r"let rec f: Num → Num = λx.x ≤ 1 ? 1 : f x - 1"
+        );
+        let ty = Type::function(Type::function(Type::Num, Type::Bool), Type::Num);
+        assert_eq!(unicode.print_type(&ty), "(Num → Bool) → Num");
+        assert_eq!(Printer::new().print_type(&ty), ty.to_string());
+    }
+
+    #[test]
+    fn test_program() {
+        let program = r"let rec f = \x.f x; let y = 1; f y";
+        let parsed = parse_program(&lexer(program).unwrap()).unwrap();
+        assert_eq!(parsed.to_string(), program);
+    }
+
+    #[test]
+    fn test_width() {
+        let expr = parse(&lexer(r"\f.\x.x<2 ? 1 : (f x-1) + (f x-2)").unwrap()).unwrap();
+        assert_eq!(
+            Printer::new().with_width(20).print(&expr),
+            "\\f.\\x.\n  x < 2\n    ? 1\n    : (f x - 1)\n      + (f x - 2)"
+        );
+        assert_eq!(
+            Printer::new().with_width(36).print(&expr),
+            "\\f.\\x.\n  x < 2 ? 1 : (f x - 1) + (f x - 2)"
+        );
+        assert_eq!(Printer::new().with_width(80).print(&expr), expr.to_string());
+    }
+
+    /// A xorshift generator, enough to build random expressions.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+
+        fn name(&mut self) -> &'static str {
+            ["a", "b", "f", "x", "y"][self.below(5) as usize]
+        }
+
+        /// A random expression, where the numbers that are not finite are
+        /// literals or, if `as_written`, the divisions they are written as.
+        fn expr(&mut self, depth: u32, as_written: bool) -> Expr {
+            let choice = if depth == 0 { 0 } else { self.below(11) };
+            let mut sub = || self.expr(depth - 1, as_written);
+            match choice {
+                0 => match self.below(5) {
+                    0 => Expr::numeric_literal(self.below(100) as f64),
+                    1 => Expr::numeric_literal(self.below(100) as f64 / 4.),
+                    2 => Expr::boolean_literal(self.below(2) == 0),
+                    3 => self.non_finite(as_written),
+                    _ => Expr::var(self.name()),
+                },
+                1 | 2 => Expr::abs(self.name(), self.expr(depth - 1, as_written)),
+                3 | 4 => Expr::app(sub(), sub()),
+                5 => {
+                    let operation = [
+                        ArithmeticOp::Add,
+                        ArithmeticOp::Sub,
+                        ArithmeticOp::Mul,
+                        ArithmeticOp::Div,
+                    ][self.below(4) as usize]
+                        .clone();
+                    Expr::arithmetic(
+                        operation,
+                        self.expr(depth - 1, as_written),
+                        self.expr(depth - 1, as_written),
+                    )
+                }
+                6 => {
+                    let operation = [
+                        ComparisonOp::Gt,
+                        ComparisonOp::Gte,
+                        ComparisonOp::Lt,
+                        ComparisonOp::Lte,
+                        ComparisonOp::Eq,
+                        ComparisonOp::Neq,
+                    ][self.below(6) as usize]
+                        .clone();
+                    Expr::comparison(
+                        operation,
+                        self.expr(depth - 1, as_written),
+                        self.expr(depth - 1, as_written),
+                    )
+                }
+                8 => {
+                    let operation = [UnaryOp::Neg, UnaryOp::Not][self.below(2) as usize].clone();
+                    Expr::unary(operation, self.expr(depth - 1, as_written))
+                }
+                9 => {
+                    let operation = [LogicalOp::And, LogicalOp::Or][self.below(2) as usize].clone();
+                    Expr::logical(
+                        operation,
+                        self.expr(depth - 1, as_written),
+                        self.expr(depth - 1, as_written),
+                    )
+                }
+                7 => Expr::conditional(
+                    self.expr(depth - 1, as_written),
+                    self.expr(depth - 1, as_written),
+                    self.expr(depth - 1, as_written),
+                ),
+                _ => {
+                    let name = self.name();
+                    let value = Expr::abs(self.name(), self.expr(depth - 1, as_written));
+                    Expr::let_rec(name, value, self.expr(depth - 1, as_written))
+                }
+            }
+        }
+
+        fn non_finite(&mut self, as_written: bool) -> Expr {
+            let one_over_zero = || {
+                let one = Expr::numeric_literal(1.);
+                Expr::arithmetic(ArithmeticOp::Div, one, Expr::numeric_literal(0.))
+            };
+            match (self.below(3), as_written) {
+                (0, false) => Expr::numeric_literal(f64::INFINITY),
+                (0, true) => one_over_zero(),
+                (1, false) => Expr::numeric_literal(f64::NEG_INFINITY),
+                (1, true) => Expr::unary(UnaryOp::Neg, one_over_zero()),
+                (_, false) => Expr::numeric_literal(f64::NAN),
+                (_, true) => {
+                    let zero = || Expr::numeric_literal(0.);
+                    Expr::arithmetic(ArithmeticOp::Div, zero(), zero())
+                }
+            }
+        }
+    }
+
+    /// The texts obtained by removing one pair of matching parentheses from `text`.
+    fn without_a_pair_of_parentheses(text: &str) -> Vec<String> {
+        let mut open = vec![];
+        let mut results = vec![];
+        for (i, c) in text.char_indices() {
+            match c {
+                '(' => open.push(i),
+                ')' => {
+                    let start = open.pop().unwrap();
+                    results.push(format!(
+                        "{}{}{}",
+                        &text[..start],
+                        &text[start + 1..i],
+                        &text[i + 1..]
+                    ));
+                }
+                _ => {}
+            }
+        }
+        results
+    }
+
+    #[test]
+    fn test_print_parse_roundtrip() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let state = random.0;
+            let expr = random.expr(5, false);
+            // The same expression, where numbers are as they are written
+            let written = Random(state).expr(5, true);
+            let printed = expr.to_string();
+            let parsed = parse(&lexer(&printed).unwrap());
+            assert_eq!(parsed.as_ref(), Ok(&written), "{}", printed);
+            let unicode = Printer::new().with_symbols(Symbols::Unicode).print(&expr);
+            assert_eq!(
+                parse(&lexer(&unicode).unwrap()),
+                Ok(written.clone()),
+                "{}",
+                unicode
+            );
+            // Every pair of parentheses is needed
+            for text in without_a_pair_of_parentheses(&printed) {
+                let parsed = lexer(&text).ok().and_then(|tokens| parse(&tokens).ok());
+                assert_ne!(parsed.as_ref(), Some(&written), "{} is {}", text, printed);
+            }
+        }
+    }
+}
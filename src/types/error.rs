@@ -0,0 +1,127 @@
+use std::{error::Error, fmt::Display};
+
+use crate::{
+    diagnostic::{Diagnostic, ToDiagnostic},
+    span::Span,
+    types::Type,
+};
+
+/// Why a program could not be typed. The variables of the types are renamed so
+/// that they start from `a` in each error.
+#[derive(Debug, PartialEq)]
+pub enum TypeError {
+    /// The expression at `span` has the type `found` where `expected` was needed.
+    Mismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    /// Typing the expression at `span` needs a type `var` equal to `ty`, which
+    /// contains it, like in `\x.x x`.
+    Infinite {
+        var: Type,
+        ty: Type,
+        span: Span,
+    },
+    /// The expression at `span` is applied to an argument but has the type
+    /// `found`, which is not a function.
+    NotAFunction {
+        found: Type,
+        span: Span,
+    },
+    /// The type of `name`, bound at `span`, must be written.
+    MissingAnnotation {
+        name: String,
+        span: Span,
+    },
+    UnboundName {
+        name: String,
+        span: Span,
+    },
+    /// The name used at `span` was defined by a definition that could not be typed.
+    Untyped {
+        name: String,
+        span: Span,
+    },
+}
+
+impl Error for TypeError {}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeError::Mismatch {
+                expected,
+                found,
+                span,
+            } => write!(
+                f,
+                "Type error at {} : expected type {}, found type {}",
+                span, expected, found
+            ),
+            TypeError::Infinite { var, ty, span } => write!(
+                f,
+                "Type error at {} : the type {} would be equal to {}, which contains it",
+                span, var, ty
+            ),
+            TypeError::NotAFunction { found, span } => write!(
+                f,
+                "Type error at {} : expected a function, found type {}",
+                span, found
+            ),
+            TypeError::MissingAnnotation { name, span } => write!(
+                f,
+                "Missing type at {} : the type of {:?} must be written",
+                span, name
+            ),
+            TypeError::UnboundName { name, span } => {
+                write!(f, "Unknown name {:?} at {} : it has no type", name, span)
+            }
+            TypeError::Untyped { name, span } => write!(
+                f,
+                "Untyped name {:?} at {} : its definition could not be typed",
+                name, span
+            ),
+        }
+    }
+}
+
+impl ToDiagnostic for TypeError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            TypeError::Mismatch {
+                expected,
+                found,
+                span,
+            } => {
+                let diagnostic = Diagnostic::new("mismatched types")
+                    .with_label(*span, format!("expected `{}`, found `{}`", expected, found));
+                match (expected, found) {
+                    (Type::Function { .. }, Type::Num | Type::Bool) => {
+                        diagnostic.with_help("only functions can be applied to an argument")
+                    }
+                    _ => diagnostic,
+                }
+            }
+            TypeError::Infinite { var, ty, span } => {
+                Diagnostic::new(format!("infinite type `{} = {}`", var, ty))
+                    .with_label(*span, "this expression would have a type containing itself")
+                    .with_help("functions like `\\x.x x` can be evaluated but have no type")
+            }
+            TypeError::NotAFunction { found, span } => Diagnostic::new("mismatched types")
+                .with_label(*span, format!("expected a function, found `{}`", found))
+                .with_help("only functions can be applied to an argument"),
+            TypeError::MissingAnnotation { name, span } => {
+                Diagnostic::new(format!("missing type for `{}`", name))
+                    .with_label(*span, format!("the type of `{}` must be written", name))
+                    .with_help("types are written after names, like in `\\x: Num.x + 1`")
+            }
+            TypeError::UnboundName { name, span } => {
+                Diagnostic::new(format!("unknown name `{}`", name))
+                    .with_label(*span, "this name is not bound to any value")
+            }
+            TypeError::Untyped { name, span } => Diagnostic::new(format!("`{}` has no type", name))
+                .with_label(*span, "the definition of this name could not be typed"),
+        }
+    }
+}
@@ -0,0 +1,681 @@
+//! Hindley-Milner type inference, which finds the most general type of an
+//! expression without annotations.
+//!
+//! Functions defined with `let rec` or by a definition are polymorphic : in
+//! `let id = \x.x; id true ? id 1 : 2`, `id` is used both as `Bool -> Bool` and
+//! as `Num -> Num`. Parameters of abstractions have a single type.
+//!
+//! Types can also be written, as in `\x: Num.x + 1` or `let rec f: Num -> Num = ...`,
+//! which inference takes into account. See `simple` to require them.
+
+pub mod error;
+pub mod simple;
+
+use std::{fmt::Display, rc::Rc};
+
+use crate::{
+    ast::{Expr, ExprKind, Item, Program, UnaryOp},
+    span::Span,
+    types::error::TypeError,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Num,
+    Bool,
+    Function {
+        parameter: Rc<Type>,
+        result: Rc<Type>,
+    },
+    /// A type variable, which stands for any type.
+    Var {
+        id: usize,
+    },
+}
+
+impl Type {
+    pub fn function(parameter: Type, result: Type) -> Self {
+        Self::Function {
+            parameter: Rc::new(parameter),
+            result: Rc::new(result),
+        }
+    }
+}
+
+/// Type variables are written `a` to `z`, then `a1` to `z1` and so on.
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Num => write!(f, "Num"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Function { parameter, result } => match **parameter {
+                Type::Function { .. } => write!(f, "({}) -> {}", parameter, result),
+                _ => write!(f, "{} -> {}", parameter, result),
+            },
+            Type::Var { id } => {
+                write!(f, "{}", char::from(b'a' + (id % 26) as u8))?;
+                if *id >= 26 {
+                    write!(f, "{}", id / 26)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A type where the variables of `generics` can be replaced by any type each
+/// time the name it belongs to is used.
+#[derive(Debug, Clone, PartialEq)]
+struct Scheme {
+    generics: Vec<usize>,
+    ty: Type,
+}
+
+impl Scheme {
+    /// A type that is the same for every use.
+    fn monomorphic(ty: Type) -> Self {
+        Self {
+            generics: vec![],
+            ty,
+        }
+    }
+}
+
+/// Renames the variables of `types` in order of appearance, starting from `a`.
+fn normalize<const N: usize>(types: [Type; N]) -> [Type; N] {
+    fn rename(ty: &Type, seen: &mut Vec<usize>) -> Type {
+        match ty {
+            Type::Num | Type::Bool => ty.clone(),
+            Type::Function { parameter, result } => {
+                let parameter = rename(parameter, seen);
+                Type::function(parameter, rename(result, seen))
+            }
+            Type::Var { id } => match seen.iter().position(|seen| seen == id) {
+                Some(id) => Type::Var { id },
+                None => {
+                    seen.push(*id);
+                    Type::Var { id: seen.len() - 1 }
+                }
+            },
+        }
+    }
+    let mut seen = vec![];
+    types.map(|ty| rename(&ty, &mut seen))
+}
+
+/// Adds the variables of `ty` that are not in `vars` yet, in order of appearance.
+fn collect_vars(ty: &Type, vars: &mut Vec<usize>) {
+    match ty {
+        Type::Var { id } => {
+            if !vars.contains(id) {
+                vars.push(*id);
+            }
+        }
+        Type::Function { parameter, result } => {
+            collect_vars(parameter, vars);
+            collect_vars(result, vars);
+        }
+        Type::Num | Type::Bool => {}
+    }
+}
+
+/// What a type variable stands for.
+#[derive(Debug, Clone)]
+enum Slot {
+    /// Nothing yet. `level` is the number of `let rec` around the place the
+    /// variable was created, the variables of a `let rec` that are deeper
+    /// than it are generalized.
+    Unbound {
+        level: usize,
+    },
+    Bound(Type),
+}
+
+/// Two types could not be unified.
+enum Failure {
+    Mismatch,
+    Infinite { var: usize, ty: Type },
+}
+
+/// The state of the inference of a single expression.
+struct Inference<'a> {
+    vars: Vec<Slot>,
+    level: usize,
+    definitions: &'a [(Rc<str>, Option<Scheme>)],
+    /// The names bound inside the expression, the innermost last.
+    locals: Vec<(Rc<str>, Scheme)>,
+}
+
+impl<'a> Inference<'a> {
+    fn new(definitions: &'a [(Rc<str>, Option<Scheme>)]) -> Self {
+        Self {
+            vars: vec![],
+            level: 0,
+            definitions,
+            locals: vec![],
+        }
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.vars.push(Slot::Unbound { level: self.level });
+        Type::Var {
+            id: self.vars.len() - 1,
+        }
+    }
+
+    /// `ty` where the variable at the top, if any, is replaced by what it stands for.
+    fn shallow(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var { id } => match &self.vars[*id] {
+                Slot::Bound(ty) => self.shallow(ty),
+                Slot::Unbound { .. } => ty.clone(),
+            },
+            _ => ty.clone(),
+        }
+    }
+
+    /// `ty` where every variable is replaced by what it stands for.
+    fn resolve(&self, ty: &Type) -> Type {
+        match self.shallow(ty) {
+            Type::Function { parameter, result } => {
+                Type::function(self.resolve(&parameter), self.resolve(&result))
+            }
+            ty => ty,
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> Result<(), Failure> {
+        match (self.shallow(a), self.shallow(b)) {
+            (Type::Num, Type::Num) | (Type::Bool, Type::Bool) => Ok(()),
+            (Type::Var { id: a }, Type::Var { id: b }) if a == b => Ok(()),
+            (Type::Var { id }, ty) | (ty, Type::Var { id }) => self.bind(id, ty),
+            (
+                Type::Function {
+                    parameter: a,
+                    result: a_result,
+                },
+                Type::Function {
+                    parameter: b,
+                    result: b_result,
+                },
+            ) => {
+                self.unify(&a, &b)?;
+                self.unify(&a_result, &b_result)
+            }
+            _ => Err(Failure::Mismatch),
+        }
+    }
+
+    fn bind(&mut self, id: usize, ty: Type) -> Result<(), Failure> {
+        let Slot::Unbound { level } = self.vars[id] else {
+            unreachable!("only unbound variables are bound");
+        };
+        if self.occurs(id, level, &ty) {
+            return Err(Failure::Infinite { var: id, ty });
+        }
+        self.vars[id] = Slot::Bound(ty);
+        Ok(())
+    }
+
+    /// Whether the variable `id` appears in `ty`. The variables of `ty` are
+    /// moved to `level` if they are deeper, as they now belong to it.
+    fn occurs(&mut self, id: usize, level: usize, ty: &Type) -> bool {
+        match self.shallow(ty) {
+            Type::Var { id: other } if other == id => true,
+            Type::Var { id: other } => {
+                if let Slot::Unbound { level: other_level } = &mut self.vars[other] {
+                    *other_level = level.min(*other_level);
+                }
+                false
+            }
+            Type::Function { parameter, result } => {
+                self.occurs(id, level, &parameter) || self.occurs(id, level, &result)
+            }
+            Type::Num | Type::Bool => false,
+        }
+    }
+
+    /// Unifies the type `found` of the expression at `span` with the one it should have.
+    fn expect(&mut self, expected: &Type, found: &Type, span: Span) -> Result<(), TypeError> {
+        self.unify(expected, found)
+            .map_err(|failure| match failure {
+                Failure::Mismatch => {
+                    let [expected, found] =
+                        normalize([self.resolve(expected), self.resolve(found)]);
+                    TypeError::Mismatch {
+                        expected,
+                        found,
+                        span,
+                    }
+                }
+                Failure::Infinite { var, ty } => {
+                    let [var, ty] = normalize([Type::Var { id: var }, self.resolve(&ty)]);
+                    TypeError::Infinite { var, ty, span }
+                }
+            })
+    }
+
+    /// The scheme of `ty`, where the variables deeper than the current level are generic.
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut generics = vec![];
+        collect_vars(&ty, &mut generics);
+        generics
+            .retain(|id| matches!(self.vars[*id], Slot::Unbound { level } if level > self.level));
+        Scheme { generics, ty }
+    }
+
+    /// A copy of the type of `scheme` with fresh variables in place of the generic ones.
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        fn replace(ty: &Type, replacements: &[(usize, Type)]) -> Type {
+            match ty {
+                Type::Var { id } => match replacements.iter().find(|(generic, _)| generic == id) {
+                    Some((_, fresh)) => fresh.clone(),
+                    None => ty.clone(),
+                },
+                Type::Function { parameter, result } => Type::function(
+                    replace(parameter, replacements),
+                    replace(result, replacements),
+                ),
+                Type::Num | Type::Bool => ty.clone(),
+            }
+        }
+        let replacements: Vec<_> = scheme
+            .generics
+            .iter()
+            .map(|generic| (*generic, self.fresh()))
+            .collect();
+        replace(&scheme.ty, &replacements)
+    }
+
+    fn lookup(&mut self, name: &str, span: Span) -> Result<Type, TypeError> {
+        if let Some((_, scheme)) = self.locals.iter().rev().find(|(local, _)| **local == *name) {
+            let scheme = scheme.clone();
+            return Ok(self.instantiate(&scheme));
+        }
+        match self
+            .definitions
+            .iter()
+            .rev()
+            .find(|(definition, _)| **definition == *name)
+        {
+            Some((_, Some(scheme))) => Ok(self.instantiate(scheme)),
+            Some((_, None)) => Err(TypeError::Untyped {
+                name: name.to_owned(),
+                span,
+            }),
+            None => Err(TypeError::UnboundName {
+                name: name.to_owned(),
+                span,
+            }),
+        }
+    }
+
+    fn infer(&mut self, expr: &Expr) -> Result<Type, TypeError> {
+        match &expr.kind {
+            ExprKind::Abs { var, ty, body } => {
+                let parameter = match ty {
+                    Some(ty) => ty.clone(),
+                    None => self.fresh(),
+                };
+                self.locals
+                    .push((Rc::clone(var), Scheme::monomorphic(parameter.clone())));
+                let result = self.infer(body);
+                self.locals.pop();
+                Ok(Type::function(parameter, result?))
+            }
+            ExprKind::App {
+                function,
+                parameter,
+            } => {
+                let function_type = self.infer(function)?;
+                let (parameter_type, result) = (self.fresh(), self.fresh());
+                self.expect(
+                    &Type::function(parameter_type.clone(), result.clone()),
+                    &function_type,
+                    function.span,
+                )?;
+                let argument = self.infer(parameter)?;
+                self.expect(&parameter_type, &argument, parameter.span)?;
+                Ok(result)
+            }
+            ExprKind::Arithmetic { lhs, rhs, .. } => {
+                for operand in [lhs, rhs] {
+                    let ty = self.infer(operand)?;
+                    self.expect(&Type::Num, &ty, operand.span)?;
+                }
+                Ok(Type::Num)
+            }
+            ExprKind::Comparison { lhs, rhs, .. } => {
+                for operand in [lhs, rhs] {
+                    let ty = self.infer(operand)?;
+                    self.expect(&Type::Num, &ty, operand.span)?;
+                }
+                Ok(Type::Bool)
+            }
+            ExprKind::Logical { lhs, rhs, .. } => {
+                for operand in [lhs, rhs] {
+                    let ty = self.infer(operand)?;
+                    self.expect(&Type::Bool, &ty, operand.span)?;
+                }
+                Ok(Type::Bool)
+            }
+            ExprKind::Unary { operation, operand } => {
+                let ty = match operation {
+                    UnaryOp::Neg => Type::Num,
+                    UnaryOp::Not => Type::Bool,
+                };
+                let found = self.infer(operand)?;
+                self.expect(&ty, &found, operand.span)?;
+                Ok(ty)
+            }
+            ExprKind::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => {
+                let ty = self.infer(condition)?;
+                self.expect(&Type::Bool, &ty, condition.span)?;
+                let ty = self.infer(true_branch)?;
+                let other = self.infer(false_branch)?;
+                self.expect(&ty, &other, false_branch.span)?;
+                Ok(ty)
+            }
+            ExprKind::Var { name } => self.lookup(name, expr.span),
+            ExprKind::NumericLiteral { .. } => Ok(Type::Num),
+            ExprKind::BooleanLiteral { .. } => Ok(Type::Bool),
+            ExprKind::LetRec {
+                name,
+                ty: annotation,
+                value,
+                body,
+            } => {
+                self.level += 1;
+                let ty = match annotation {
+                    Some(ty) => ty.clone(),
+                    None => self.fresh(),
+                };
+                self.locals
+                    .push((Rc::clone(name), Scheme::monomorphic(ty.clone())));
+                let value_type = self.infer(value);
+                self.locals.pop();
+                let value_type = value_type?;
+                self.expect(&ty, &value_type, value.span)?;
+                self.level -= 1;
+                let scheme = self.generalize(&ty);
+                self.locals.push((Rc::clone(name), scheme));
+                let result = self.infer(body);
+                self.locals.pop();
+                result
+            }
+            // Invalid expressions can't be evaluated, whatever their type.
+            ExprKind::Error => Ok(self.fresh()),
+        }
+    }
+}
+
+/// How types are found.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TypeSystem {
+    /// Hindley-Milner inference, types can be written but don't need to.
+    #[default]
+    Inferred,
+    /// The simply typed lambda calculus, where the types of parameters and of
+    /// `let rec` must be written.
+    Simple,
+}
+
+/// Finds the types of programs one after the other, knowing the definitions
+/// made by the previous ones.
+#[derive(Debug, Clone, Default)]
+pub struct TypeChecker {
+    /// The scheme of each definition, or `None` if it could not be typed.
+    definitions: Vec<(Rc<str>, Option<Scheme>)>,
+    system: TypeSystem,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_system(self, system: TypeSystem) -> Self {
+        Self { system, ..self }
+    }
+
+    /// The type of `expr`, with variables starting from `a`.
+    pub fn type_of(&self, expr: &Expr) -> Result<Type, TypeError> {
+        match self.system {
+            TypeSystem::Inferred => {
+                let mut inference = Inference::new(&self.definitions);
+                let ty = inference.infer(expr)?;
+                let [ty] = normalize([inference.resolve(&ty)]);
+                Ok(ty)
+            }
+            TypeSystem::Simple => simple::type_of(expr, &self.definitions),
+        }
+    }
+
+    /// The type of `expr` as the value of a definition, every variable being generic.
+    fn scheme(&self, expr: &Expr) -> Result<Scheme, TypeError> {
+        let ty = self.type_of(expr)?;
+        let mut generics = vec![];
+        collect_vars(&ty, &mut generics);
+        Ok(Scheme { generics, ty })
+    }
+
+    /// Makes `name` known to the following programs, as untyped if `value`
+    /// can't be typed.
+    pub fn define(&mut self, name: Rc<str>, value: &Expr) {
+        let scheme = self.scheme(value).ok();
+        self.definitions.push((name, scheme));
+    }
+
+    /// Infers the type of each item of `program`, and returns the ones of the
+    /// expressions. Definitions made before an error are kept.
+    pub fn check(&mut self, program: &Program) -> Result<Vec<Type>, TypeError> {
+        let mut types = vec![];
+        for item in &program.items {
+            match item {
+                Item::Definition { name, value } => {
+                    let scheme = self.scheme(value)?;
+                    self.definitions.push((Rc::clone(name), Some(scheme)));
+                }
+                Item::Expression(expr) => types.push(self.type_of(expr)?),
+            }
+        }
+        Ok(types)
+    }
+}
+
+impl Expr {
+    /// The most general type of the expression, with variables starting from `a`.
+    pub fn infer_type(&self) -> Result<Type, TypeError> {
+        TypeChecker::new().type_of(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lexer::lexer,
+        parser::{parse, parse_program},
+        types::*,
+    };
+
+    fn infer(program: &str) -> Result<String, TypeError> {
+        parse(&lexer(program).unwrap())
+            .unwrap()
+            .infer_type()
+            .map(|ty| ty.to_string())
+    }
+
+    #[test]
+    fn test_inference() {
+        for (program, expected) in [
+            ("1 + 2", "Num"),
+            ("1 < 2 ? true : false", "Bool"),
+            (r"\x.x", "a -> a"),
+            (r"\x.x + 1", "Num -> Num"),
+            (r"\x.\y.x", "a -> b -> a"),
+            (r"\f.\x.f (f x)", "(a -> a) -> a -> a"),
+            (r"\f.\g.\x.f (g x)", "(a -> b) -> (c -> a) -> c -> b"),
+            (
+                r"let rec fib = \x.x<2 ? 1 : (fib x-1) + (fib x-2) in fib",
+                "Num -> Num",
+            ),
+            (r"let rec f = \x.f x in f", "a -> b"),
+            (r"\x.-x", "Num -> Num"),
+            (r"\x.!x", "Bool -> Bool"),
+            (r"\x.\y.x && y || x", "Bool -> Bool -> Bool"),
+        ] {
+            assert_eq!(infer(program).as_deref(), Ok(expected), "{}", program);
+        }
+    }
+
+    #[test]
+    fn test_let_polymorphism() {
+        assert_eq!(
+            infer(r"let rec id = \x.x in id true ? id 1 : 2").as_deref(),
+            Ok("Num")
+        );
+        // Parameters are not polymorphic
+        assert!(infer(r"(\id.id true ? id 1 : 2) \x.x").is_err());
+
+        let mut checker = TypeChecker::new();
+        let program = parse_program(&lexer(r"let id = \x.x; id; id 1; id true").unwrap()).unwrap();
+        let types: Vec<_> = checker
+            .check(&program)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(types, ["a -> a", "Num", "Bool"]);
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let Err(TypeError::Mismatch {
+            expected,
+            found,
+            span,
+        }) = infer("1+1 2")
+        else {
+            panic!("expected a mismatch");
+        };
+        assert_eq!(
+            (expected.to_string(), found),
+            ("a -> b".to_string(), Type::Num)
+        );
+        assert_eq!((span.start.offset, span.end.offset), (0, 3));
+
+        assert!(matches!(
+            infer("true ? 1 : false"),
+            Err(TypeError::Mismatch {
+                expected: Type::Num,
+                found: Type::Bool,
+                ..
+            })
+        ));
+        let Err(TypeError::Infinite { var, ty, .. }) = infer(r"\x.x x") else {
+            panic!("expected an infinite type");
+        };
+        assert_eq!(format!("{} = {}", var, ty), "a = a -> b");
+        assert!(matches!(infer("y"), Err(TypeError::UnboundName { .. })));
+        assert!(matches!(
+            infer("!1"),
+            Err(TypeError::Mismatch {
+                expected: Type::Bool,
+                found: Type::Num,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_untyped_definitions() {
+        let mut checker = TypeChecker::new();
+        let y = parse(&lexer(r"\f.(\x.f (\v.x x v)) (\x.f (\v.x x v))").unwrap()).unwrap();
+        checker.define(Rc::from("Y"), &y);
+        checker.define(Rc::from("one"), &Expr::numeric_literal(1.));
+        let expr = parse(&lexer("Y one").unwrap()).unwrap();
+        assert!(matches!(
+            checker.type_of(&expr),
+            Err(TypeError::Untyped { name, .. }) if name == "Y"
+        ));
+        assert_eq!(checker.type_of(&Expr::var("one")), Ok(Type::Num));
+    }
+
+    #[test]
+    fn test_annotations() {
+        assert_eq!(infer(r"\x: Bool.x").as_deref(), Ok("Bool -> Bool"));
+        assert_eq!(
+            infer(r"let rec f: Num -> Num = \x.f x in f").as_deref(),
+            Ok("Num -> Num")
+        );
+        assert!(matches!(
+            infer(r"(\x: Bool.x) 1"),
+            Err(TypeError::Mismatch {
+                expected: Type::Bool,
+                found: Type::Num,
+                ..
+            })
+        ));
+    }
+
+    fn simple(program: &str) -> Result<Vec<String>, TypeError> {
+        let program = parse_program(&lexer(program).unwrap()).unwrap();
+        let types = TypeChecker::new()
+            .with_system(TypeSystem::Simple)
+            .check(&program)?;
+        Ok(types.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn test_simply_typed() {
+        assert_eq!(
+            simple(r"\x: Num.x + 1; \f: Num -> Num.f 2; (\b: Bool.b ? 1 : 2) true; \b: Bool.!b"),
+            Ok(vec![
+                "Num -> Num".to_string(),
+                "(Num -> Num) -> Num".to_string(),
+                "Num".to_string(),
+                "Bool -> Bool".to_string()
+            ])
+        );
+        assert_eq!(
+            simple(concat!(
+                r"let rec fib: Num -> Num = \x: Num.x<2 ? 1 : (fib x-1) + (fib x-2); ",
+                "fib"
+            )),
+            Ok(vec!["Num -> Num".to_string()])
+        );
+        let Err(TypeError::MissingAnnotation { name, span }) = simple(r"(\x: Num.x) ((\y.y) 1)")
+        else {
+            panic!("expected a missing annotation");
+        };
+        assert_eq!(name, "y");
+        assert_eq!((span.start.offset, span.end.offset), (14, 18));
+        assert!(matches!(
+            simple(r"let rec f = \x: Num.f x in f"),
+            Err(TypeError::MissingAnnotation { .. })
+        ));
+        let Err(TypeError::Mismatch {
+            expected,
+            found,
+            span,
+        }) = simple(r"(\f: Num -> Num.f 2) \x: Num.x > 1")
+        else {
+            panic!("expected a mismatch");
+        };
+        assert_eq!(expected.to_string(), "Num -> Num");
+        assert_eq!(found.to_string(), "Num -> Bool");
+        assert_eq!((span.start.offset, span.end.offset), (21, 34));
+        assert!(matches!(
+            simple("1+1 2"),
+            Err(TypeError::NotAFunction {
+                found: Type::Num,
+                ..
+            })
+        ));
+    }
+}
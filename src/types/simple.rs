@@ -0,0 +1,170 @@
+//! Type checking of the simply typed lambda calculus, where the type of every
+//! parameter is written : `\x: Num.x + 1`, `\f: Num -> Num.f 2`. Nothing is
+//! inferred beyond the type of expressions from the type of their parts.
+
+use std::rc::Rc;
+
+use crate::{
+    ast::{Expr, ExprKind, UnaryOp},
+    span::Span,
+    types::{error::TypeError, Scheme, Type},
+};
+
+/// The type of `expr`, where the names that are not bound in it are
+/// looked up in `definitions`.
+pub(super) fn type_of(
+    expr: &Expr,
+    definitions: &[(Rc<str>, Option<Scheme>)],
+) -> Result<Type, TypeError> {
+    Checker {
+        definitions,
+        locals: vec![],
+    }
+    .check(expr)
+}
+
+struct Checker<'a> {
+    definitions: &'a [(Rc<str>, Option<Scheme>)],
+    /// The names bound inside the expression, the innermost last.
+    locals: Vec<(Rc<str>, Type)>,
+}
+
+impl Checker<'_> {
+    fn lookup(&self, name: &str, span: Span) -> Result<Type, TypeError> {
+        if let Some((_, ty)) = self.locals.iter().rev().find(|(local, _)| **local == *name) {
+            return Ok(ty.clone());
+        }
+        match self
+            .definitions
+            .iter()
+            .rev()
+            .find(|(definition, _)| **definition == *name)
+        {
+            Some((_, Some(scheme))) => Ok(scheme.ty.clone()),
+            Some((_, None)) => Err(TypeError::Untyped {
+                name: name.to_owned(),
+                span,
+            }),
+            None => Err(TypeError::UnboundName {
+                name: name.to_owned(),
+                span,
+            }),
+        }
+    }
+
+    /// Checks that the expression at `span` has the type `expected`.
+    fn expect(&self, expected: &Type, found: Type, span: Span) -> Result<(), TypeError> {
+        if *expected == found {
+            Ok(())
+        } else {
+            Err(TypeError::Mismatch {
+                expected: expected.clone(),
+                found,
+                span,
+            })
+        }
+    }
+
+    /// The type of `expr` when `name` is bound to `ty`.
+    fn check_with(&mut self, name: &Rc<str>, ty: Type, expr: &Expr) -> Result<Type, TypeError> {
+        self.locals.push((Rc::clone(name), ty));
+        let result = self.check(expr);
+        self.locals.pop();
+        result
+    }
+
+    fn check(&mut self, expr: &Expr) -> Result<Type, TypeError> {
+        match &expr.kind {
+            ExprKind::Abs { var, ty, body } => {
+                let Some(parameter) = ty else {
+                    return Err(TypeError::MissingAnnotation {
+                        name: var.to_string(),
+                        span: expr.span,
+                    });
+                };
+                let result = self.check_with(var, parameter.clone(), body)?;
+                Ok(Type::function(parameter.clone(), result))
+            }
+            ExprKind::App {
+                function,
+                parameter,
+            } => match self.check(function)? {
+                Type::Function {
+                    parameter: expected,
+                    result,
+                } => {
+                    let argument = self.check(parameter)?;
+                    self.expect(&expected, argument, parameter.span)?;
+                    Ok(Rc::unwrap_or_clone(result))
+                }
+                found => Err(TypeError::NotAFunction {
+                    found,
+                    span: function.span,
+                }),
+            },
+            ExprKind::Arithmetic { lhs, rhs, .. } => {
+                for operand in [lhs, rhs] {
+                    let ty = self.check(operand)?;
+                    self.expect(&Type::Num, ty, operand.span)?;
+                }
+                Ok(Type::Num)
+            }
+            ExprKind::Comparison { lhs, rhs, .. } => {
+                for operand in [lhs, rhs] {
+                    let ty = self.check(operand)?;
+                    self.expect(&Type::Num, ty, operand.span)?;
+                }
+                Ok(Type::Bool)
+            }
+            ExprKind::Logical { lhs, rhs, .. } => {
+                for operand in [lhs, rhs] {
+                    let ty = self.check(operand)?;
+                    self.expect(&Type::Bool, ty, operand.span)?;
+                }
+                Ok(Type::Bool)
+            }
+            ExprKind::Unary { operation, operand } => {
+                let ty = match operation {
+                    UnaryOp::Neg => Type::Num,
+                    UnaryOp::Not => Type::Bool,
+                };
+                let found = self.check(operand)?;
+                self.expect(&ty, found, operand.span)?;
+                Ok(ty)
+            }
+            ExprKind::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => {
+                let ty = self.check(condition)?;
+                self.expect(&Type::Bool, ty, condition.span)?;
+                let ty = self.check(true_branch)?;
+                let other = self.check(false_branch)?;
+                self.expect(&ty, other, false_branch.span)?;
+                Ok(ty)
+            }
+            ExprKind::Var { name } => self.lookup(name, expr.span),
+            ExprKind::NumericLiteral { .. } => Ok(Type::Num),
+            ExprKind::BooleanLiteral { .. } => Ok(Type::Bool),
+            ExprKind::LetRec {
+                name,
+                ty,
+                value,
+                body,
+            } => {
+                let Some(ty) = ty else {
+                    return Err(TypeError::MissingAnnotation {
+                        name: name.to_string(),
+                        span: expr.span,
+                    });
+                };
+                let value_type = self.check_with(name, ty.clone(), value)?;
+                self.expect(ty, value_type, value.span)?;
+                self.check_with(name, ty.clone(), body)
+            }
+            // Invalid expressions can't be evaluated, whatever their type.
+            ExprKind::Error => Ok(Type::Var { id: 0 }),
+        }
+    }
+}